@@ -0,0 +1,40 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Embeds the git commit and build time for `GET /version`. `GIT_COMMIT` overrides
+// the git lookup for builds without a `.git` directory (e.g. Docker), and
+// `SOURCE_DATE_EPOCH` pins the timestamp for reproducible builds.
+fn main() {
+    let commit = std::env::var("GIT_COMMIT")
+        .ok()
+        .filter(|commit| !commit.is_empty())
+        .or_else(|| {
+            let output = Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .output()
+                .ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        });
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=GIT_COMMIT={}", commit);
+    }
+
+    let build_epoch = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", build_epoch);
+
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
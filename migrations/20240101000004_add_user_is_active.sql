@@ -0,0 +1,2 @@
+-- Add soft deactivation flag to users
+ALTER TABLE users ADD COLUMN IF NOT EXISTS is_active BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,27 @@
+-- Track when each user's item collection last changed (including deletions),
+-- used for Last-Modified / If-Modified-Since on the item list
+CREATE TABLE IF NOT EXISTS item_list_versions (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    modified_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO item_list_versions (user_id, modified_at)
+SELECT user_id, MAX(updated_at) FROM items GROUP BY user_id
+ON CONFLICT (user_id) DO NOTHING;
+
+CREATE OR REPLACE FUNCTION touch_item_list_version()
+RETURNS TRIGGER AS $$
+DECLARE
+    owner UUID := COALESCE(NEW.user_id, OLD.user_id);
+BEGIN
+    -- Skip when the owner itself is being deleted (cascading item deletes)
+    INSERT INTO item_list_versions (user_id, modified_at)
+    SELECT owner, CURRENT_TIMESTAMP
+    WHERE EXISTS (SELECT 1 FROM users WHERE id = owner)
+    ON CONFLICT (user_id) DO UPDATE SET modified_at = EXCLUDED.modified_at;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER touch_item_list_version AFTER INSERT OR UPDATE OR DELETE ON items
+    FOR EACH ROW EXECUTE FUNCTION touch_item_list_version();
@@ -0,0 +1,2 @@
+-- Bumped to invalidate every token issued to the user so far
+ALTER TABLE users ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
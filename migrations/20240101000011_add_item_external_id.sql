@@ -0,0 +1,15 @@
+-- Identifier owned by an external system, used for idempotent upserts.
+-- NULLs are distinct, so items without one never conflict.
+ALTER TABLE items ADD COLUMN IF NOT EXISTS external_id VARCHAR(255);
+
+-- ADD CONSTRAINT has no IF NOT EXISTS
+DO $$
+BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conname = 'items_user_external_id_key' AND conrelid = 'items'::regclass
+    ) THEN
+        ALTER TABLE items ADD CONSTRAINT items_user_external_id_key UNIQUE (user_id, external_id);
+    END IF;
+END
+$$;
@@ -0,0 +1,2 @@
+-- Per-user settings such as the default item sort
+ALTER TABLE users ADD COLUMN IF NOT EXISTS preferences JSONB NOT NULL DEFAULT '{}';
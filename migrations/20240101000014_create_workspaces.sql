@@ -0,0 +1,90 @@
+-- Items belong to a workspace; users see the items of every workspace they are a
+-- member of. Each user gets a personal workspace (personal_user_id set), which
+-- holds their items by default and goes away with them.
+CREATE TABLE IF NOT EXISTS workspaces (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(100) NOT NULL,
+    personal_user_id UUID UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TRIGGER update_workspaces_updated_at BEFORE UPDATE ON workspaces
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TABLE IF NOT EXISTS workspace_members (
+    workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('owner', 'editor', 'viewer')),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_workspace_members_user_id ON workspace_members(user_id);
+
+-- Backfill a personal workspace for every existing user and move their items into it
+INSERT INTO workspaces (name, personal_user_id)
+SELECT 'Personal', id FROM users
+ON CONFLICT (personal_user_id) DO NOTHING;
+
+INSERT INTO workspace_members (workspace_id, user_id, role)
+SELECT id, personal_user_id, 'owner' FROM workspaces WHERE personal_user_id IS NOT NULL
+ON CONFLICT DO NOTHING;
+
+ALTER TABLE items ADD COLUMN IF NOT EXISTS workspace_id UUID REFERENCES workspaces(id) ON DELETE CASCADE;
+
+UPDATE items SET workspace_id = workspaces.id
+FROM workspaces
+WHERE workspaces.personal_user_id = items.user_id AND items.workspace_id IS NULL;
+
+ALTER TABLE items ALTER COLUMN workspace_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_items_workspace_id ON items(workspace_id);
+
+-- New users get their personal workspace as part of the insert
+CREATE OR REPLACE FUNCTION create_personal_workspace()
+RETURNS TRIGGER AS $$
+DECLARE
+    workspace UUID;
+BEGIN
+    INSERT INTO workspaces (name, personal_user_id) VALUES ('Personal', NEW.id)
+    RETURNING id INTO workspace;
+    INSERT INTO workspace_members (workspace_id, user_id, role) VALUES (workspace, NEW.id, 'owner');
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER create_personal_workspace AFTER INSERT ON users
+    FOR EACH ROW EXECUTE FUNCTION create_personal_workspace();
+
+-- An item change is a change to the list of every member of its workspace
+CREATE OR REPLACE FUNCTION touch_item_list_version()
+RETURNS TRIGGER AS $$
+BEGIN
+    -- Skip members that are themselves being deleted (cascading deletes)
+    INSERT INTO item_list_versions (user_id, modified_at)
+    SELECT members.user_id, CURRENT_TIMESTAMP
+    FROM workspace_members members
+    WHERE members.workspace_id IN (NEW.workspace_id, OLD.workspace_id)
+      AND EXISTS (SELECT 1 FROM users WHERE id = members.user_id)
+    ON CONFLICT (user_id) DO UPDATE SET modified_at = EXCLUDED.modified_at;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+-- Joining or leaving a workspace changes which items a user sees
+CREATE OR REPLACE FUNCTION touch_member_item_list_version()
+RETURNS TRIGGER AS $$
+DECLARE
+    member UUID := COALESCE(NEW.user_id, OLD.user_id);
+BEGIN
+    INSERT INTO item_list_versions (user_id, modified_at)
+    SELECT member, CURRENT_TIMESTAMP
+    WHERE EXISTS (SELECT 1 FROM users WHERE id = member)
+    ON CONFLICT (user_id) DO UPDATE SET modified_at = EXCLUDED.modified_at;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER touch_member_item_list_version AFTER INSERT OR DELETE ON workspace_members
+    FOR EACH ROW EXECUTE FUNCTION touch_member_item_list_version();
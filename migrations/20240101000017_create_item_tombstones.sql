@@ -0,0 +1,26 @@
+-- Deleted items leave a tombstone so delta syncs (GET /items?since=) can tell
+-- clients to drop them. Tombstones outlive their workspace on purpose and are
+-- purged by the cleanup task after the retention period.
+CREATE TABLE IF NOT EXISTS item_tombstones (
+    id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL,
+    deleted_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_item_tombstones_workspace_deleted
+    ON item_tombstones(workspace_id, deleted_at);
+
+CREATE INDEX IF NOT EXISTS idx_items_workspace_updated
+    ON items(workspace_id, updated_at);
+
+CREATE OR REPLACE FUNCTION record_item_tombstone()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO item_tombstones (id, workspace_id) VALUES (OLD.id, OLD.workspace_id)
+    ON CONFLICT (id) DO UPDATE SET deleted_at = EXCLUDED.deleted_at;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER record_item_tombstone AFTER DELETE ON items
+    FOR EACH ROW EXECUTE FUNCTION record_item_tombstone();
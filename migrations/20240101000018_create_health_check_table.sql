@@ -0,0 +1,6 @@
+-- Single row written by the deep readiness check (DEEP_HEALTH_CHECK=true). The
+-- write is always rolled back; the table only has to exist and accept it.
+CREATE TABLE IF NOT EXISTS health_check (
+    id SMALLINT PRIMARY KEY CHECK (id = 1),
+    checked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
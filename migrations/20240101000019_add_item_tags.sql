@@ -0,0 +1,4 @@
+-- Free-form labels on items, kept in the order given and without duplicates
+ALTER TABLE items ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_items_tags ON items USING GIN (tags);
@@ -0,0 +1,9 @@
+-- Pending email address changes, applied once the new address proves it receives mail.
+-- One per user: requesting another change replaces the previous one
+CREATE TABLE IF NOT EXISTS email_changes (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    new_email VARCHAR(255) NOT NULL,
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
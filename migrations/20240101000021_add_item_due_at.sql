@@ -0,0 +1,5 @@
+-- Optional due date for task-style items, used by the `?overdue=` and
+-- `?due_before=`/`?due_after=` list filters
+ALTER TABLE items ADD COLUMN IF NOT EXISTS due_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_items_due_at ON items(due_at) WHERE due_at IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Archived items are hidden from the default item list but kept intact and can be
+-- restored at any time; deletion still removes an item for good
+ALTER TABLE items ADD COLUMN IF NOT EXISTS archived_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_items_archived_at ON items(archived_at) WHERE archived_at IS NOT NULL;
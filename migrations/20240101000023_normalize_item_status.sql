@@ -0,0 +1,4 @@
+-- Statuses are now trimmed and lowercased on write and in filters; bring rows
+-- written before that into the same form so `?status=` still finds them
+UPDATE items SET status = LOWER(BTRIM(status, E' \t\r\n'))
+WHERE status <> LOWER(BTRIM(status, E' \t\r\n'));
@@ -0,0 +1,2 @@
+-- Storage quotas sum each user's attachment sizes before accepting an upload
+CREATE INDEX IF NOT EXISTS idx_attachments_user_id ON attachments(user_id);
@@ -0,0 +1,18 @@
+-- Manual ordering for `?sort=position`. New items take the next value of the
+-- sequence and so land at the end; reordering swaps positions among the moved
+-- items, so gaps left by deletions are harmless
+CREATE SEQUENCE IF NOT EXISTS items_position_seq;
+
+ALTER TABLE items ADD COLUMN IF NOT EXISTS position BIGINT;
+
+UPDATE items SET position = ordered.n
+FROM (SELECT id, ROW_NUMBER() OVER (ORDER BY created_at, id) AS n FROM items) ordered
+WHERE items.id = ordered.id AND items.position IS NULL;
+
+SELECT setval('items_position_seq', COALESCE(MAX(position), 0) + 1, false) FROM items;
+
+ALTER TABLE items ALTER COLUMN position SET DEFAULT nextval('items_position_seq');
+ALTER TABLE items ALTER COLUMN position SET NOT NULL;
+ALTER SEQUENCE items_position_seq OWNED BY items.position;
+
+CREATE INDEX IF NOT EXISTS idx_items_position ON items(position);
@@ -0,0 +1,4 @@
+-- Optional public profile, set with PATCH /auth/me
+ALTER TABLE users ADD COLUMN IF NOT EXISTS display_name VARCHAR(100);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar_url VARCHAR(2048);
+ALTER TABLE users ADD COLUMN IF NOT EXISTS bio TEXT;
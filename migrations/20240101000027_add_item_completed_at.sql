@@ -0,0 +1,27 @@
+-- When the item last became `done`, for time-to-completion reporting. A trigger
+-- keeps it, so every way of changing a status (updates, bulk changes, upserts,
+-- items created as done) sets it alike: stamped on entering `done`, kept while
+-- the item stays done, cleared when it leaves
+ALTER TABLE items ADD COLUMN IF NOT EXISTS completed_at TIMESTAMP WITH TIME ZONE;
+
+-- For items already done, their last change is the best guess
+UPDATE items SET completed_at = updated_at WHERE status = 'done' AND completed_at IS NULL;
+
+CREATE OR REPLACE FUNCTION set_item_completed_at()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.status <> 'done' THEN
+        NEW.completed_at := NULL;
+    ELSIF TG_OP = 'INSERT' OR OLD.status <> 'done' THEN
+        NEW.completed_at := CURRENT_TIMESTAMP;
+    ELSE
+        NEW.completed_at := OLD.completed_at;
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER set_item_completed_at BEFORE INSERT OR UPDATE ON items
+    FOR EACH ROW EXECUTE FUNCTION set_item_completed_at();
+
+CREATE INDEX IF NOT EXISTS idx_items_completed_at ON items(completed_at) WHERE completed_at IS NOT NULL;
@@ -0,0 +1,21 @@
+-- Leaving a workspace is remembered like a deletion, so delta syncs
+-- (GET /items?since=) can tell the member's clients to drop its items. Purged by
+-- the cleanup task after the same retention period as item tombstones.
+CREATE TABLE IF NOT EXISTS workspace_departures (
+    user_id UUID NOT NULL,
+    workspace_id UUID NOT NULL,
+    left_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, workspace_id)
+);
+
+CREATE OR REPLACE FUNCTION record_workspace_departure()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO workspace_departures (user_id, workspace_id) VALUES (OLD.user_id, OLD.workspace_id)
+    ON CONFLICT (user_id, workspace_id) DO UPDATE SET left_at = EXCLUDED.left_at;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER record_workspace_departure AFTER DELETE ON workspace_members
+    FOR EACH ROW EXECUTE FUNCTION record_workspace_departure();
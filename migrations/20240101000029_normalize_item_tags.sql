@@ -0,0 +1,18 @@
+-- Tags saved before they were normalized may differ only in case or whitespace.
+-- Rewrite them the way `normalize_tag` does (whitespace runs collapsed to one
+-- space, trimmed, lowercased) and drop the repeats this creates, keeping each
+-- tag where it first appeared. Only items whose tags change are updated
+WITH normalized AS (
+    SELECT items.id, ARRAY(
+        SELECT tag
+        FROM unnest(items.tags) WITH ORDINALITY AS t(raw, ord),
+            LATERAL (SELECT lower(btrim(regexp_replace(raw, '\s+', ' ', 'g'))) AS tag) AS n
+        WHERE tag <> ''
+        GROUP BY tag
+        ORDER BY MIN(ord)
+    ) AS tags
+    FROM items
+)
+UPDATE items SET tags = normalized.tags
+FROM normalized
+WHERE items.id = normalized.id AND items.tags <> normalized.tags;
@@ -0,0 +1,5 @@
+-- When an admin deactivated the account. Unlike deactivating your own account,
+-- this can't be undone with POST /auth/reactivate, only by an admin. Accounts
+-- deactivated before this column existed can't be told apart and stay
+-- self-service
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locked_at TIMESTAMP WITH TIME ZONE;
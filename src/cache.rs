@@ -0,0 +1,67 @@
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
+
+use crate::{kv::KeyValueStore, models::User};
+
+/// TTL cache of user rows for `GET /auth/me`, kept in the shared key-value store.
+///
+/// Writes made through the API invalidate the entry; anything else (the CLI,
+/// manual SQL) is only picked up once the entry expires, so responses can be up
+/// to `ttl` stale. Without Redis each instance caches separately and misses
+/// invalidations made by the others. A zero TTL disables the cache.
+pub struct UserCache {
+    ttl: Duration,
+    store: Arc<dyn KeyValueStore>,
+}
+
+impl UserCache {
+    pub fn new(ttl_secs: u64, store: Arc<dyn KeyValueStore>) -> Self {
+        Self {
+            ttl: Duration::from_secs(ttl_secs),
+            store,
+        }
+    }
+
+    /// The cached user, if any. Store failures count as a miss.
+    pub async fn get(&self, user_id: Uuid) -> Option<User> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+
+        match self.store.get(&cache_key(user_id)).await {
+            Ok(value) => value.and_then(|value| serde_json::from_slice(&value).ok()),
+            Err(e) => {
+                tracing::warn!("User cache lookup failed: {}", e);
+                None
+            }
+        }
+    }
+
+    pub async fn insert(&self, user: &User) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let Ok(value) = serde_json::to_vec(user) else {
+            return;
+        };
+        if let Err(e) = self.store.set(&cache_key(user.id), &value, self.ttl).await {
+            tracing::warn!("User cache insert failed: {}", e);
+        }
+    }
+
+    /// Drops the user's entry; call after any write to their row.
+    pub async fn invalidate(&self, user_id: Uuid) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        if let Err(e) = self.store.delete(&cache_key(user_id)).await {
+            tracing::warn!("User cache invalidation failed: {}", e);
+        }
+    }
+}
+
+fn cache_key(user_id: Uuid) -> String {
+    format!("user:{}", user_id)
+}
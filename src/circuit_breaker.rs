@@ -0,0 +1,172 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+tokio::task_local! {
+    static DATABASE_FAILED: Cell<bool>;
+}
+
+/// Stops sending requests to a database that keeps failing. After `threshold`
+/// requests in a row hit a connection-level database error, new requests are
+/// turned away for `cooldown`; then a single request is let through as a probe,
+/// and its outcome closes the breaker again or restarts the cooldown.
+///
+/// A request counts as a success when it finishes without such an error, even if
+/// it never touched the database. A zero threshold disables the breaker.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BreakerState {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        since: Instant,
+    },
+    /// The probe is in flight; everyone else keeps waiting.
+    HalfOpen,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown_secs: u64) -> Self {
+        Self {
+            threshold,
+            cooldown: Duration::from_secs(cooldown_secs),
+            state: Mutex::new(BreakerState::Closed { failures: 0 }),
+        }
+    }
+
+    /// Runs `f` unless the breaker is open, in which case returns how long until
+    /// the next probe. Database failures inside `f` are the ones `From<sqlx::Error>`
+    /// reports through `note_database_failure`.
+    pub async fn call<F: Future>(&self, f: F) -> Result<F::Output, Duration> {
+        if self.threshold == 0 {
+            return Ok(f.await);
+        }
+
+        let probe = self.admit()?.then_some(Probe { breaker: self });
+        let (output, failed) = DATABASE_FAILED
+            .scope(Cell::new(false), async {
+                let output = f.await;
+                (output, DATABASE_FAILED.with(Cell::get))
+            })
+            .await;
+        // Finished, so the outcome is recorded instead
+        std::mem::forget(probe);
+        self.record(failed);
+
+        Ok(output)
+    }
+
+    /// `closed`, `open`, `half_open` or `disabled`, for the readiness check.
+    pub fn state(&self) -> &'static str {
+        if self.threshold == 0 {
+            return "disabled";
+        }
+
+        match *self.lock() {
+            BreakerState::Closed { .. } => "closed",
+            BreakerState::Open { .. } => "open",
+            BreakerState::HalfOpen => "half_open",
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BreakerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lets a request through, returning whether it is the half-open probe.
+    fn admit(&self) -> Result<bool, Duration> {
+        let mut state = self.lock();
+        match *state {
+            BreakerState::Closed { .. } => Ok(false),
+            BreakerState::Open { since } => {
+                let elapsed = since.elapsed();
+                if elapsed >= self.cooldown {
+                    *state = BreakerState::HalfOpen;
+                    Ok(true)
+                } else {
+                    Err(self.cooldown - elapsed)
+                }
+            }
+            // Ask callers to come back once the probe had time to finish
+            BreakerState::HalfOpen => Err(Duration::from_secs(1)),
+        }
+    }
+
+    fn record(&self, failed: bool) {
+        let mut state = self.lock();
+        *state = match (*state, failed) {
+            (BreakerState::Closed { failures }, true) if failures + 1 < self.threshold => {
+                BreakerState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (BreakerState::Closed { .. } | BreakerState::HalfOpen, true) => {
+                tracing::warn!(
+                    "Database circuit breaker open; rejecting requests for {:?}",
+                    self.cooldown
+                );
+                BreakerState::Open {
+                    since: Instant::now(),
+                }
+            }
+            (BreakerState::HalfOpen, false) => {
+                tracing::info!("Database circuit breaker closed");
+                BreakerState::Closed { failures: 0 }
+            }
+            (BreakerState::Closed { .. }, false) => BreakerState::Closed { failures: 0 },
+            // Requests admitted before the breaker opened don't change it
+            (open @ BreakerState::Open { .. }, _) => open,
+        };
+    }
+}
+
+/// Held while the half-open probe runs. Dropped only when the probe is cancelled
+/// (its client went away mid-request): there is no outcome to record then, but
+/// the breaker must not stay half open for good, so the cooldown starts over.
+struct Probe<'a> {
+    breaker: &'a CircuitBreaker,
+}
+
+impl Drop for Probe<'_> {
+    fn drop(&mut self) {
+        let mut state = self.breaker.lock();
+        if let BreakerState::HalfOpen = *state {
+            *state = BreakerState::Open {
+                since: Instant::now(),
+            };
+        }
+    }
+}
+
+/// Marks the current request as having hit a database connection failure. Does
+/// nothing outside `CircuitBreaker::call`.
+pub fn note_database_failure() {
+    let _ = DATABASE_FAILED.try_with(|failed| failed.set(true));
+}
+
+/// Errors that mean the database can't be reached or has stopped serving, as
+/// opposed to a query that failed on its own.
+pub fn is_connection_error(e: &sqlx::Error) -> bool {
+    match e {
+        sqlx::Error::PoolTimedOut
+        | sqlx::Error::PoolClosed
+        | sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::Protocol(_)
+        | sqlx::Error::WorkerCrashed => true,
+        // Class 08 is connection exceptions; 57P01-57P03 are shutdowns and startup
+        sqlx::Error::Database(db) => db
+            .code()
+            .is_some_and(|code| code.starts_with("08") || code.starts_with("57P")),
+        _ => false,
+    }
+}
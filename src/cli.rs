@@ -0,0 +1,78 @@
+use anyhow::Context;
+use sqlx::PgPool;
+use validator::Validate;
+
+use crate::{
+    config::Config,
+    models::{CreateUser, ROLE_ADMIN},
+    utils::{auth::hash_password, username::normalize_username},
+};
+
+const USAGE: &str = "Usage: rust-backend-starter create-admin <email> <username>  (password read from ADMIN_PASSWORD)";
+
+/// Runs a one-off administrative command if one was given on the command line.
+/// Returns `Ok(true)` when a command ran and the server should not start.
+pub async fn run(args: &[String], db: &PgPool, config: &Config) -> anyhow::Result<bool> {
+    match args.first().map(String::as_str) {
+        None => Ok(false),
+        Some("create-admin") => {
+            let [email, username] = &args[1..] else {
+                anyhow::bail!(USAGE);
+            };
+            create_admin(db, config, email, username).await?;
+            Ok(true)
+        }
+        Some(other) => anyhow::bail!("Unknown command '{}'. {}", other, USAGE),
+    }
+}
+
+/// Creates an admin account, or promotes the existing account with that email.
+async fn create_admin(
+    db: &PgPool,
+    config: &Config,
+    email: &str,
+    username: &str,
+) -> anyhow::Result<()> {
+    let promoted = sqlx::query("UPDATE users SET role = $1 WHERE email = $2")
+        .bind(ROLE_ADMIN)
+        .bind(email)
+        .execute(db)
+        .await
+        .context("Failed to promote existing user")?;
+
+    if promoted.rows_affected() > 0 {
+        tracing::info!("✅ Promoted existing user {} to admin", email);
+        return Ok(());
+    }
+
+    let password = std::env::var("ADMIN_PASSWORD")
+        .context("ADMIN_PASSWORD must be set to create a new admin account")?;
+
+    let username = normalize_username(username, config.username_charset, config.username_case_fold)
+        .map_err(anyhow::Error::msg)
+        .context("Invalid admin account details")?;
+    let payload = CreateUser {
+        email: email.to_string(),
+        username,
+        password,
+        invite_token: None,
+    };
+    payload.validate().context("Invalid admin account details")?;
+
+    let password_hash = hash_password(&payload.password, config.bcrypt_cost)
+        .await
+        .context("Failed to hash password")?;
+
+    sqlx::query("INSERT INTO users (email, username, password_hash, role) VALUES ($1, $2, $3, $4)")
+        .bind(&payload.email)
+        .bind(&payload.username)
+        .bind(&password_hash)
+        .bind(ROLE_ADMIN)
+        .execute(db)
+        .await
+        .context("Failed to create admin user")?;
+
+    tracing::info!("✅ Created admin user {}", email);
+
+    Ok(())
+}
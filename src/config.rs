@@ -1,21 +1,205 @@
 use anyhow::Context;
+use axum::http::HeaderValue;
 use serde::Deserialize;
 use std::env;
 
+use crate::{
+    models::{normalize_status, ITEM_STATUSES},
+    utils::{auth::generate_token, cookie::CSRF_COOKIE_NAME},
+};
+
+/// Who may use `POST /auth/signup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum SignupMode {
+    Open,
+    InviteOnly,
+    Closed,
+}
+
+/// TLS requirement for database connections, overriding `sslmode` in the URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum DbSslMode {
+    Disable,
+    Require,
+    VerifyFull,
+}
+
+/// `SameSite` attribute of the auth cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum CookieSameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl CookieSameSite {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CookieSameSite::Strict => "Strict",
+            CookieSameSite::Lax => "Lax",
+            CookieSameSite::None => "None",
+        }
+    }
+}
+
+/// Characters allowed in usernames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum UsernameCharset {
+    /// Any letters, digits, punctuation and spaces; control and invisible characters are refused.
+    Unicode,
+    /// ASCII letters, digits, `.`, `_` and `-`, which rules out look-alike letters.
+    Ascii,
+}
+
+/// What the `sub` claim of issued JWTs identifies the user by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum JwtSubject {
+    Id,
+    Email,
+}
+
+/// How timestamps in response bodies are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TimestampFormat {
+    Rfc3339,
+    EpochMillis,
+}
+
+impl TimestampFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "rfc3339" => Some(TimestampFormat::Rfc3339),
+            "epoch_millis" => Some(TimestampFormat::EpochMillis),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimestampFormat::Rfc3339 => "rfc3339",
+            TimestampFormat::EpochMillis => "epoch_millis",
+        }
+    }
+}
+
+/// Where uploaded blobs (item attachments) are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum StorageBackend {
+    Local,
+    S3,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub host: String,
     pub port: u16,
     pub database_url: String,
+    pub db_ssl_mode: Option<DbSslMode>,
+    pub db_ssl_root_cert: Option<String>,
+    pub warm_pool: bool,
+    /// `DATABASE_URL` points at pgbouncer in transaction pooling mode.
+    pub db_pgbouncer_mode: bool,
     pub jwt_secret: String,
+    /// Set when `JWT_SECRET` was unset outside production and a random one was made up.
+    pub jwt_secret_generated: bool,
     pub jwt_expiration: i64,
+    pub jwt_audiences: Vec<String>,
+    pub jwt_subject: JwtSubject,
+    /// Longest bearer token read, in bytes; longer ones are refused unparsed.
+    pub jwt_max_length: usize,
     pub app_env: String,
+    pub log_filter: String,
+    pub verify_user_on_request: bool,
+    /// Tie new tokens to the client's fingerprint and refuse them from other clients.
+    pub bind_token_to_client: bool,
+    pub signup_mode: SignupMode,
+    pub username_charset: UsernameCharset,
+    /// Lowercase usernames, so `Alice` and `alice` are the same account.
+    pub username_case_fold: bool,
+    pub invite_expiration: i64,
+    pub log_body_sample_rate: f64,
+    pub log_body_max_bytes: usize,
+    pub maintenance_mode: bool,
+    pub maintenance_retry_after: Option<u64>,
+    pub enforce_status_transitions: bool,
+    pub pool_stats_interval_secs: u64,
+    pub cleanup_interval_secs: u64,
+    pub items_notify_enabled: bool,
+    pub reject_unknown_fields: bool,
+    /// Request bodies may nest arrays and objects this many levels deep.
+    pub json_max_depth: usize,
+    pub tls_enabled: bool,
+    pub force_https: bool,
+    /// `host[:port]` the `FORCE_HTTPS` redirect points at, never taken from the request.
+    pub public_host: Option<String>,
+    pub hsts_max_age: u64,
+    pub nosniff_header: bool,
+    pub frame_deny_header: bool,
+    pub content_security_policy: Option<String>,
+    pub cache_user_ttl_secs: u64,
+    pub attachments_dir: String,
+    pub attachment_max_bytes: u64,
+    /// Total attachment bytes each user may store (`0`: no limit).
+    pub max_storage_bytes_per_user: u64,
+    pub attachment_content_types: Vec<String>,
+    pub storage_backend: StorageBackend,
+    pub storage_presign_expiry_secs: u64,
+    pub s3_bucket: Option<String>,
+    pub s3_region: String,
+    pub s3_endpoint: Option<String>,
+    pub s3_path_style: bool,
+    pub auth_cookie_enabled: bool,
+    pub auth_cookie_name: String,
+    pub auth_cookie_same_site: CookieSameSite,
+    pub auth_cookie_secure: bool,
+    pub single_flight_enabled: bool,
+    pub default_item_status: String,
+    pub max_tags_per_item: usize,
+    pub due_at_max_past_secs: i64,
+    pub workspaces_enabled: bool,
+    pub log_exclude_paths: Vec<String>,
+    pub user_rate_limit: u32,
+    pub user_rate_limit_window_secs: u64,
+    /// Each user may download one data export per this many seconds (`0`: no limit).
+    pub data_export_window_secs: u64,
+    pub redis_url: Option<String>,
+    pub deep_health_check: bool,
+    pub bcrypt_cost: u32,
+    pub timestamp_format: TimestampFormat,
+    pub db_breaker_threshold: u32,
+    pub db_breaker_cooldown_secs: u64,
+    /// Requests handled at once outside the health checks (`0`: no limit).
+    pub request_concurrency_limit: usize,
+    /// Requests waiting for one of those slots before more are shed with `503`.
+    pub request_queue_depth: usize,
+    /// How long a handler may take to start its response (`0`: no limit).
+    pub request_timeout_secs: u64,
+    /// Per-route replacements for `request_timeout_secs`, keyed by route path.
+    pub request_timeouts: Vec<(String, u64)>,
+    pub query_count_enabled: bool,
+    pub auth_cors_origins: Vec<String>,
+    /// `Cache-Control` directives for successful reads, keyed by route path.
+    pub cache_control_routes: Vec<(String, String)>,
+    /// JSON Schema files request bodies are checked against, keyed by `METHOD /path`.
+    pub json_schemas: Vec<(String, String)>,
 }
 
 impl Config {
     pub fn from_env() -> anyhow::Result<Self> {
         dotenvy::dotenv().ok();
 
+        let app_env = env::var("APP_ENV").unwrap_or_else(|_| "development".to_string());
+
+        // Outside production a missing secret gets a throwaway one, so the app starts
+        // without setup; tokens it signs stop verifying once the process exits
+        let jwt_secret = env::var("JWT_SECRET").ok().filter(|secret| !secret.is_empty());
+        let jwt_secret_generated = jwt_secret.is_none();
+        let jwt_secret = match jwt_secret {
+            Some(secret) => secret,
+            None if app_env != "production" => generate_token(GENERATED_JWT_SECRET_LEN),
+            None => anyhow::bail!("JWT_SECRET must be set"),
+        };
+
         Ok(Self {
             host: env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
             port: env::var("PORT")
@@ -24,17 +208,480 @@ impl Config {
                 .context("PORT must be a valid number")?,
             database_url: env::var("DATABASE_URL")
                 .context("DATABASE_URL must be set")?,
-            jwt_secret: env::var("JWT_SECRET")
-                .context("JWT_SECRET must be set")?,
-            jwt_expiration: env::var("JWT_EXPIRATION")
-                .unwrap_or_else(|_| "86400".to_string())
-                .parse()
-                .context("JWT_EXPIRATION must be a valid number")?,
-            app_env: env::var("APP_ENV")
-                .unwrap_or_else(|_| "development".to_string()),
+            db_ssl_mode: match env::var("DB_SSL_MODE").unwrap_or_default().as_str() {
+                "" => None,
+                "disable" => Some(DbSslMode::Disable),
+                "require" => Some(DbSslMode::Require),
+                "verify-full" => Some(DbSslMode::VerifyFull),
+                other => anyhow::bail!(
+                    "DB_SSL_MODE must be disable, require or verify-full (got {other:?})"
+                ),
+            },
+            db_ssl_root_cert: env::var("DB_SSL_ROOT_CERT").ok().filter(|path| !path.is_empty()),
+            warm_pool: env::var("WARM_POOL")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("WARM_POOL must be true or false")?,
+            db_pgbouncer_mode: env::var("DB_PGBOUNCER_MODE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("DB_PGBOUNCER_MODE must be true or false")?,
+            jwt_secret,
+            jwt_secret_generated,
+            jwt_expiration: parse_duration_secs(
+                &env::var("JWT_EXPIRATION").unwrap_or_else(|_| "24h".to_string()),
+            )
+            .context("JWT_EXPIRATION must be a number of seconds or a duration like 30m, 24h, 7d")?,
+            jwt_audiences: env::var("JWT_AUDIENCES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|aud| !aud.is_empty())
+                .map(String::from)
+                .collect(),
+            jwt_subject: match env::var("JWT_SUBJECT")
+                .unwrap_or_else(|_| "id".to_string())
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "id" => JwtSubject::Id,
+                "email" => JwtSubject::Email,
+                other => anyhow::bail!("JWT_SUBJECT must be id or email (got {other:?})"),
+            },
+            jwt_max_length: env::var("JWT_MAX_LENGTH")
+                .unwrap_or_else(|_| "8192".to_string())
+                .parse()
+                .ok()
+                .filter(|len| *len > 0)
+                .context("JWT_MAX_LENGTH must be a positive number of bytes")?,
+            log_filter: env::var("LOG_FILTER")
+                .unwrap_or_else(|_| default_log_filter(&app_env).to_string()),
+            app_env,
+            verify_user_on_request: env::var("VERIFY_USER_ON_REQUEST")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("VERIFY_USER_ON_REQUEST must be true or false")?,
+            bind_token_to_client: env::var("BIND_TOKEN_TO_CLIENT")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("BIND_TOKEN_TO_CLIENT must be true or false")?,
+            signup_mode: match env::var("ALLOW_SIGNUP")
+                .unwrap_or_else(|_| "true".to_string())
+                .as_str()
+            {
+                "true" | "open" => SignupMode::Open,
+                "invite" | "invite-only" => SignupMode::InviteOnly,
+                "false" | "closed" => SignupMode::Closed,
+                other => anyhow::bail!("ALLOW_SIGNUP must be true, false or invite (got {other:?})"),
+            },
+            username_charset: match env::var("USERNAME_CHARSET")
+                .unwrap_or_else(|_| "unicode".to_string())
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "unicode" => UsernameCharset::Unicode,
+                "ascii" => UsernameCharset::Ascii,
+                other => anyhow::bail!("USERNAME_CHARSET must be unicode or ascii (got {other:?})"),
+            },
+            username_case_fold: env::var("USERNAME_CASE_FOLD")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("USERNAME_CASE_FOLD must be true or false")?,
+            invite_expiration: parse_duration_secs(
+                &env::var("INVITE_EXPIRATION").unwrap_or_else(|_| "7d".to_string()),
+            )
+            .context("INVITE_EXPIRATION must be a number of seconds or a duration like 7d")?,
+            log_body_sample_rate: env::var("LOG_BODY_SAMPLE_RATE")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .ok()
+                .filter(|rate: &f64| (0.0..=1.0).contains(rate))
+                .context("LOG_BODY_SAMPLE_RATE must be a number between 0 and 1")?,
+            log_body_max_bytes: env::var("LOG_BODY_MAX_BYTES")
+                .unwrap_or_else(|_| "16384".to_string())
+                .parse()
+                .context("LOG_BODY_MAX_BYTES must be a valid number")?,
+            maintenance_mode: env::var("MAINTENANCE_MODE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("MAINTENANCE_MODE must be true or false")?,
+            maintenance_retry_after: env::var("MAINTENANCE_RETRY_AFTER")
+                .ok()
+                .map(|v| parse_duration_secs(&v).map(|secs| secs as u64))
+                .transpose()
+                .context("MAINTENANCE_RETRY_AFTER must be a number of seconds or a duration like 5m")?,
+            enforce_status_transitions: env::var("ENFORCE_STATUS_TRANSITIONS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("ENFORCE_STATUS_TRANSITIONS must be true or false")?,
+            pool_stats_interval_secs: parse_duration_secs(
+                &env::var("POOL_STATS_INTERVAL_SECS").unwrap_or_else(|_| "60".to_string()),
+            )
+            .context("POOL_STATS_INTERVAL_SECS must be a number of seconds or a duration like 1m")?
+                as u64,
+            cleanup_interval_secs: parse_duration_secs(
+                &env::var("CLEANUP_INTERVAL_SECS").unwrap_or_else(|_| "1h".to_string()),
+            )
+            .context("CLEANUP_INTERVAL_SECS must be a number of seconds or a duration like 1h")?
+                as u64,
+            items_notify_enabled: env::var("ITEMS_NOTIFY_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("ITEMS_NOTIFY_ENABLED must be true or false")?,
+            reject_unknown_fields: env::var("REJECT_UNKNOWN_FIELDS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("REJECT_UNKNOWN_FIELDS must be true or false")?,
+            // serde_json stops at 128 levels on its own
+            json_max_depth: match env::var("JSON_MAX_DEPTH")
+                .unwrap_or_else(|_| "32".to_string())
+                .parse()
+            {
+                Ok(depth @ 1..=128) => depth,
+                _ => anyhow::bail!("JSON_MAX_DEPTH must be a number between 1 and 128"),
+            },
+            tls_enabled: env::var("TLS_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("TLS_ENABLED must be true or false")?,
+            force_https: env::var("FORCE_HTTPS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("FORCE_HTTPS must be true or false")?,
+            public_host: match env::var("PUBLIC_HOST").ok().filter(|host| !host.is_empty()) {
+                Some(host) => {
+                    host.parse::<axum::http::uri::Authority>()
+                        .ok()
+                        .filter(|authority| authority.as_str() == host && !host.contains('@'))
+                        .context("PUBLIC_HOST must be a host name with an optional port")?;
+                    Some(host)
+                }
+                None => None,
+            },
+            hsts_max_age: parse_duration_secs(
+                &env::var("HSTS_MAX_AGE").unwrap_or_else(|_| "365d".to_string()),
+            )
+            .context("HSTS_MAX_AGE must be a number of seconds or a duration like 365d")?
+                as u64,
+            nosniff_header: env::var("NOSNIFF_HEADER")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("NOSNIFF_HEADER must be true or false")?,
+            frame_deny_header: env::var("FRAME_DENY_HEADER")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("FRAME_DENY_HEADER must be true or false")?,
+            content_security_policy: match env::var("CONTENT_SECURITY_POLICY") {
+                Ok(policy) if policy.trim().is_empty() => None,
+                Ok(policy) if HeaderValue::from_str(&policy).is_err() => {
+                    anyhow::bail!("CONTENT_SECURITY_POLICY must be a valid header value")
+                }
+                Ok(policy) => Some(policy),
+                Err(_) => Some(DEFAULT_CONTENT_SECURITY_POLICY.to_string()),
+            },
+            cache_user_ttl_secs: parse_duration_secs(
+                &env::var("CACHE_USER_TTL_SECS").unwrap_or_else(|_| "0".to_string()),
+            )
+            .context("CACHE_USER_TTL_SECS must be a number of seconds or a duration like 30s")?
+                as u64,
+            attachments_dir: env::var("ATTACHMENTS_DIR")
+                .unwrap_or_else(|_| "./data/attachments".to_string()),
+            attachment_max_bytes: env::var("ATTACHMENT_MAX_BYTES")
+                .unwrap_or_else(|_| "10485760".to_string())
+                .parse()
+                .context("ATTACHMENT_MAX_BYTES must be a valid number")?,
+            max_storage_bytes_per_user: env::var("MAX_STORAGE_BYTES_PER_USER")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("MAX_STORAGE_BYTES_PER_USER must be a valid number")?,
+            attachment_content_types: env::var("ATTACHMENT_CONTENT_TYPES")
+                .unwrap_or_else(|_| DEFAULT_ATTACHMENT_CONTENT_TYPES.to_string())
+                .split(',')
+                .map(|content_type| content_type.trim().to_ascii_lowercase())
+                .filter(|content_type| !content_type.is_empty())
+                .collect(),
+            storage_backend: match env::var("STORAGE_BACKEND")
+                .unwrap_or_else(|_| "local".to_string())
+                .as_str()
+            {
+                "local" => StorageBackend::Local,
+                "s3" => StorageBackend::S3,
+                other => anyhow::bail!("STORAGE_BACKEND must be local or s3 (got {other:?})"),
+            },
+            storage_presign_expiry_secs: parse_duration_secs(
+                &env::var("STORAGE_PRESIGN_EXPIRY").unwrap_or_else(|_| "15m".to_string()),
+            )
+            .context("STORAGE_PRESIGN_EXPIRY must be a number of seconds or a duration like 15m")?
+                as u64,
+            s3_bucket: env::var("S3_BUCKET").ok().filter(|bucket| !bucket.is_empty()),
+            s3_region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            s3_endpoint: env::var("S3_ENDPOINT").ok().filter(|endpoint| !endpoint.is_empty()),
+            s3_path_style: env::var("S3_PATH_STYLE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("S3_PATH_STYLE must be true or false")?,
+            auth_cookie_enabled: env::var("AUTH_COOKIE_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("AUTH_COOKIE_ENABLED must be true or false")?,
+            auth_cookie_name: match env::var("AUTH_COOKIE_NAME") {
+                Ok(name) if !is_valid_cookie_name(&name) => {
+                    anyhow::bail!("AUTH_COOKIE_NAME must be a valid cookie name (got {name:?})")
+                }
+                Ok(name) => name,
+                Err(_) => "access_token".to_string(),
+            },
+            auth_cookie_same_site: match env::var("AUTH_COOKIE_SAME_SITE")
+                .unwrap_or_else(|_| "strict".to_string())
+                .to_ascii_lowercase()
+                .as_str()
+            {
+                "strict" => CookieSameSite::Strict,
+                "lax" => CookieSameSite::Lax,
+                "none" => CookieSameSite::None,
+                other => anyhow::bail!(
+                    "AUTH_COOKIE_SAME_SITE must be strict, lax or none (got {other:?})"
+                ),
+            },
+            auth_cookie_secure: env::var("AUTH_COOKIE_SECURE")
+                .unwrap_or_else(|_| "true".to_string())
+                .parse()
+                .context("AUTH_COOKIE_SECURE must be true or false")?,
+            single_flight_enabled: env::var("SINGLE_FLIGHT_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("SINGLE_FLIGHT_ENABLED must be true or false")?,
+            default_item_status: match env::var("DEFAULT_ITEM_STATUS")
+                .map(|status| normalize_status(&status))
+            {
+                Ok(status) if !ITEM_STATUSES.contains(&status.as_str()) => anyhow::bail!(
+                    "DEFAULT_ITEM_STATUS must be one of {} (got {status:?})",
+                    ITEM_STATUSES.join(", ")
+                ),
+                Ok(status) => status,
+                // Matches the column default, which stays as a fallback for direct inserts
+                Err(_) => "active".to_string(),
+            },
+            max_tags_per_item: env::var("MAX_TAGS_PER_ITEM")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .context("MAX_TAGS_PER_ITEM must be a valid number")?,
+            due_at_max_past_secs: parse_duration_secs(
+                &env::var("DUE_AT_MAX_PAST").unwrap_or_else(|_| "1d".to_string()),
+            )
+            .context("DUE_AT_MAX_PAST must be a number of seconds or a duration like 12h, 7d")?,
+            workspaces_enabled: env::var("WORKSPACES_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("WORKSPACES_ENABLED must be true or false")?,
+            log_exclude_paths: env::var("LOG_EXCLUDE_PATHS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|path| path.trim().to_string())
+                .filter(|path| !path.is_empty())
+                .collect(),
+            user_rate_limit: env::var("USER_RATE_LIMIT")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("USER_RATE_LIMIT must be a number of requests")?,
+            user_rate_limit_window_secs: parse_duration_secs(
+                &env::var("USER_RATE_LIMIT_WINDOW").unwrap_or_else(|_| "60".to_string()),
+            )
+            .context("USER_RATE_LIMIT_WINDOW must be a number of seconds or a duration like 1m")?
+                as u64,
+            data_export_window_secs: parse_duration_secs(
+                &env::var("DATA_EXPORT_WINDOW").unwrap_or_else(|_| "1h".to_string()),
+            )
+            .context("DATA_EXPORT_WINDOW must be a number of seconds or a duration like 1h")?
+                as u64,
+            redis_url: env::var("REDIS_URL").ok().filter(|url| !url.is_empty()),
+            deep_health_check: env::var("DEEP_HEALTH_CHECK")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("DEEP_HEALTH_CHECK must be true or false")?,
+            bcrypt_cost: match env::var("BCRYPT_COST")
+                .unwrap_or_else(|_| bcrypt::DEFAULT_COST.to_string())
+                .parse()
+            {
+                Ok(cost @ 4..=MAX_BCRYPT_COST) => cost,
+                _ => anyhow::bail!(
+                    "BCRYPT_COST must be a number between 4 and {}",
+                    MAX_BCRYPT_COST
+                ),
+            },
+            timestamp_format: match env::var("TIMESTAMP_FORMAT") {
+                Ok(value) => TimestampFormat::parse(&value).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "TIMESTAMP_FORMAT must be rfc3339 or epoch_millis (got {value:?})"
+                    )
+                })?,
+                Err(_) => TimestampFormat::Rfc3339,
+            },
+            db_breaker_threshold: env::var("DB_BREAKER_THRESHOLD")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("DB_BREAKER_THRESHOLD must be a number of failures")?,
+            db_breaker_cooldown_secs: parse_duration_secs(
+                &env::var("DB_BREAKER_COOLDOWN").unwrap_or_else(|_| "30s".to_string()),
+            )
+            .context("DB_BREAKER_COOLDOWN must be a number of seconds or a duration like 30s")?
+                as u64,
+            request_concurrency_limit: env::var("REQUEST_CONCURRENCY_LIMIT")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .context("REQUEST_CONCURRENCY_LIMIT must be a number of requests")?,
+            request_queue_depth: match env::var("REQUEST_QUEUE_DEPTH")
+                .unwrap_or_else(|_| "100".to_string())
+                .parse()
+            {
+                Ok(depth) if depth >= 1 => depth,
+                _ => anyhow::bail!("REQUEST_QUEUE_DEPTH must be a number of requests, at least 1"),
+            },
+            request_timeout_secs: parse_duration_secs(
+                &env::var("REQUEST_TIMEOUT").unwrap_or_else(|_| "60s".to_string()),
+            )
+            .context("REQUEST_TIMEOUT must be a number of seconds or a duration like 30s")?
+                as u64,
+            request_timeouts: env::var("REQUEST_TIMEOUTS")
+                .unwrap_or_default()
+                .split(';')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| {
+                    let (path, timeout) = entry
+                        .split_once('=')
+                        .map(|(path, timeout)| (path.trim(), timeout.trim()))
+                        .filter(|(path, _)| path.starts_with('/'))
+                        .and_then(|(path, timeout)| {
+                            Some((path, parse_duration_secs(timeout).ok()? as u64))
+                        })
+                        .with_context(|| {
+                            format!(
+                                "REQUEST_TIMEOUTS entries must look like \
+                                 /auth/me/export=5m (got {entry:?})"
+                            )
+                        })?;
+                    Ok((path.to_string(), timeout))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            query_count_enabled: env::var("QUERY_COUNT_ENABLED")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .context("QUERY_COUNT_ENABLED must be true or false")?,
+            auth_cors_origins: env::var("AUTH_CORS_ORIGINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(|origin| {
+                    if origin == "*" || HeaderValue::from_str(origin).is_err() {
+                        anyhow::bail!(
+                            "AUTH_CORS_ORIGINS must list origins like https://app.example.com \
+                             (got {origin:?})"
+                        );
+                    }
+                    Ok(origin.to_string())
+                })
+                .collect::<anyhow::Result<_>>()?,
+            cache_control_routes: env::var("CACHE_CONTROL_ROUTES")
+                .unwrap_or_default()
+                .split(';')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| {
+                    let (path, directives) = entry
+                        .split_once('=')
+                        .map(|(path, directives)| (path.trim(), directives.trim()))
+                        .filter(|(path, directives)| {
+                            path.starts_with('/')
+                                && !directives.is_empty()
+                                && HeaderValue::from_str(directives).is_ok()
+                        })
+                        .with_context(|| {
+                            format!(
+                                "CACHE_CONTROL_ROUTES entries must look like \
+                                 /items=private, max-age=30 (got {entry:?})"
+                            )
+                        })?;
+                    Ok((path.to_string(), directives.to_string()))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            json_schemas: env::var("JSON_SCHEMAS")
+                .unwrap_or_default()
+                .split(';')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| {
+                    let (route, file) = entry
+                        .split_once('=')
+                        .map(|(route, file)| (route.trim(), file.trim()))
+                        .filter(|(route, file)| {
+                            route.split_once(' ').is_some_and(|(method, path)| {
+                                !method.is_empty()
+                                    && method.chars().all(|c| c.is_ascii_uppercase())
+                                    && path.starts_with('/')
+                            }) && !file.is_empty()
+                        })
+                        .with_context(|| {
+                            format!(
+                                "JSON_SCHEMAS entries must look like \
+                                 POST /items=schemas/create_item.json (got {entry:?})"
+                            )
+                        })?;
+                    Ok((route.to_string(), file.to_string()))
+                })
+                .collect::<anyhow::Result<_>>()?,
         })
     }
 
+    /// Checks settings that parse fine but are unsafe. Refuses to start in
+    /// production; only warns in other environments.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.jwt_secret_generated {
+            tracing::warn!(
+                "⚠️  JWT_SECRET is not set; using a random secret, so tokens won't survive a restart"
+            );
+        } else if let Some(problem) = jwt_secret_problem(&self.jwt_secret) {
+            let remediation = "Set JWT_SECRET to a random value of at least 32 bytes, e.g. `openssl rand -base64 48`";
+            if self.is_production() {
+                anyhow::bail!("Insecure JWT_SECRET: {problem}. {remediation}");
+            }
+            tracing::warn!("⚠️  Insecure JWT_SECRET: {}. {}", problem, remediation);
+        }
+
+        // A zero lifetime issues tokens that are already expired, locking everyone out
+        if self.jwt_expiration <= 0 {
+            anyhow::bail!("JWT_EXPIRATION must be greater than zero");
+        }
+
+        if self.db_ssl_mode == Some(DbSslMode::VerifyFull) && self.db_ssl_root_cert.is_none() {
+            anyhow::bail!(
+                "DB_SSL_MODE=verify-full requires DB_SSL_ROOT_CERT (the CA certificate of the database server)"
+            );
+        }
+
+        if self.auth_cookie_enabled && self.auth_cookie_name == CSRF_COOKIE_NAME {
+            anyhow::bail!(
+                "AUTH_COOKIE_NAME must not be {CSRF_COOKIE_NAME:?}, which holds the CSRF token"
+            );
+        }
+
+        // Browsers drop SameSite=None cookies that aren't also Secure
+        if self.auth_cookie_enabled
+            && self.auth_cookie_same_site == CookieSameSite::None
+            && !self.auth_cookie_secure
+        {
+            anyhow::bail!("AUTH_COOKIE_SAME_SITE=none requires AUTH_COOKIE_SECURE=true");
+        }
+
+        // The Host header is client-controlled, so redirecting to it would be an open redirect
+        if self.force_https && self.public_host.is_none() {
+            anyhow::bail!("FORCE_HTTPS=true requires PUBLIC_HOST (e.g. api.example.com)");
+        }
+
+        Ok(())
+    }
+
     pub fn is_production(&self) -> bool {
         self.app_env == "production"
     }
@@ -43,3 +690,158 @@ impl Config {
         format!("{}:{}", self.host, self.port)
     }
 }
+
+/// Nothing on a JSON API should load sub-resources or be framed.
+const DEFAULT_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; frame-ancestors 'none'";
+
+/// Uploads accepted when `ATTACHMENT_CONTENT_TYPES` is not set.
+const DEFAULT_ATTACHMENT_CONTENT_TYPES: &str =
+    "image/png,image/jpeg,image/gif,image/webp,application/pdf,text/plain";
+
+/// Tracing filter used when neither `RUST_LOG` nor `LOG_FILTER` is set.
+fn default_log_filter(app_env: &str) -> &'static str {
+    match app_env {
+        "production" => "rust_backend_starter=info,tower_http=info,sqlx=warn",
+        _ => "rust_backend_starter=debug,tower_http=debug,sqlx=warn",
+    }
+}
+
+/// Cookie names are RFC 9110 tokens: visible ASCII without separators.
+fn is_valid_cookie_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !"()<>@,;:\\\"/[]?={}".contains(c))
+}
+
+const MIN_JWT_SECRET_LEN: usize = 32;
+
+/// Each step doubles the work; 14 already takes around a second per login on typical hardware.
+const MAX_BCRYPT_COST: u32 = 14;
+
+/// Length of the secret made up when `JWT_SECRET` is unset outside production.
+const GENERATED_JWT_SECRET_LEN: usize = 64;
+
+/// Placeholder fragments from the example env files and common throwaway values.
+const DEFAULT_JWT_SECRET_MARKERS: &[&str] = &[
+    "change-this",
+    "changeme",
+    "your-super-secret",
+    "your-super-secure",
+    "secret-key",
+    "development-only",
+];
+
+pub(crate) fn jwt_secret_problem(secret: &str) -> Option<String> {
+    let lowered = secret.to_lowercase();
+
+    if secret.trim().is_empty() {
+        Some("it is empty".to_string())
+    } else if DEFAULT_JWT_SECRET_MARKERS.iter().any(|marker| lowered.contains(marker)) {
+        Some("it looks like a placeholder/default value".to_string())
+    } else if secret.len() < MIN_JWT_SECRET_LEN {
+        Some(format!(
+            "it is {} bytes long, at least {MIN_JWT_SECRET_LEN} are required",
+            secret.len()
+        ))
+    } else {
+        None
+    }
+}
+
+/// Parses a duration given either as plain seconds (`86400`) or with unit
+/// suffixes (`30s`, `15m`, `24h`, `7d`, `1w`), which may be combined (`1h30m`).
+fn parse_duration_secs(value: &str) -> anyhow::Result<i64> {
+    let value = value.trim();
+    if value.is_empty() {
+        anyhow::bail!("duration is empty");
+    }
+
+    if let Ok(secs) = value.parse::<i64>() {
+        if secs < 0 {
+            anyhow::bail!("duration must not be negative: {value}");
+        }
+        return Ok(secs);
+    }
+
+    let mut total: i64 = 0;
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let multiplier = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => anyhow::bail!("invalid duration unit '{c}' in {value:?}"),
+        };
+        let amount: i64 = digits
+            .parse()
+            .with_context(|| format!("missing number before '{c}' in {value:?}"))?;
+        total = amount
+            .checked_mul(multiplier)
+            .and_then(|secs| total.checked_add(secs))
+            .with_context(|| format!("duration {value:?} is too large"))?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() {
+        anyhow::bail!("missing unit after '{digits}' in {value:?}");
+    }
+
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_accepts_bare_seconds() {
+        assert_eq!(parse_duration_secs("86400").unwrap(), 86400);
+        assert_eq!(parse_duration_secs("0").unwrap(), 0);
+    }
+
+    #[test]
+    fn duration_accepts_each_suffix() {
+        assert_eq!(parse_duration_secs("30s").unwrap(), 30);
+        assert_eq!(parse_duration_secs("15m").unwrap(), 15 * 60);
+        assert_eq!(parse_duration_secs("24h").unwrap(), 24 * 60 * 60);
+        assert_eq!(parse_duration_secs("7d").unwrap(), 7 * 24 * 60 * 60);
+        assert_eq!(parse_duration_secs("1w").unwrap(), 7 * 24 * 60 * 60);
+    }
+
+    #[test]
+    fn duration_adds_up_combined_units() {
+        assert_eq!(parse_duration_secs("1h30m").unwrap(), 90 * 60);
+        assert_eq!(parse_duration_secs("1d12h").unwrap(), 36 * 60 * 60);
+        assert_eq!(parse_duration_secs("1m1m").unwrap(), 120);
+    }
+
+    #[test]
+    fn duration_ignores_surrounding_whitespace_only() {
+        assert_eq!(parse_duration_secs("  24h\n").unwrap(), 24 * 60 * 60);
+        assert_eq!(parse_duration_secs(" 60 ").unwrap(), 60);
+        assert!(parse_duration_secs("1h 30m").is_err());
+        assert!(parse_duration_secs("   ").is_err());
+    }
+
+    #[test]
+    fn duration_rejects_overflow() {
+        assert!(parse_duration_secs("9223372036854775807w").is_err());
+        assert!(parse_duration_secs("9223372036854775807s1s").is_err());
+        assert!(parse_duration_secs("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn duration_rejects_invalid_and_negative_input() {
+        for value in ["", "abc", "10x", "h", "1h30", "1.5h", "-5", "-5m", "5-m"] {
+            assert!(parse_duration_secs(value).is_err(), "{value:?} should be rejected");
+        }
+    }
+}
@@ -1,25 +1,119 @@
 use anyhow::Context;
-use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::time::Duration;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions, PgSslMode},
+    PgPool,
+};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, DbSslMode};
+
+/// Connections the pool keeps open even when idle.
+const MIN_CONNECTIONS: u32 = 5;
+
+/// How long a query waits for a free connection before failing. Request timeouts
+/// must be longer, or a starved pool looks like slow handlers rather than errors.
+pub const ACQUIRE_TIMEOUT_SECS: u64 = 30;
+
+pub async fn create_pool(config: &Config) -> anyhow::Result<PgPool> {
+    let mut options: PgConnectOptions = config
+        .database_url
+        .parse()
+        .context("DATABASE_URL is not a valid PostgreSQL connection string")?;
+
+    // An explicit DB_SSL_MODE overrides any `sslmode` in the URL
+    if let Some(ssl_mode) = config.db_ssl_mode {
+        options = options.ssl_mode(match ssl_mode {
+            DbSslMode::Disable => PgSslMode::Disable,
+            DbSslMode::Require => PgSslMode::Require,
+            DbSslMode::VerifyFull => PgSslMode::VerifyFull,
+        });
+    }
+    if let Some(root_cert) = &config.db_ssl_root_cert {
+        anyhow::ensure!(
+            std::path::Path::new(root_cert).is_file(),
+            "DB_SSL_ROOT_CERT {root_cert:?} does not exist or is not a file"
+        );
+        options = options.ssl_root_cert(root_cert);
+    }
+
+    // Transaction pooling runs each transaction on whichever server connection is
+    // free, so statements prepared on one can't be looked up later. pgbouncer keeps
+    // its server connections open itself; idle client connections would only hold
+    // on to its slots
+    let min_connections = if config.db_pgbouncer_mode {
+        options = options.statement_cache_capacity(0);
+        0
+    } else {
+        MIN_CONNECTIONS
+    };
 
-pub async fn create_pool(database_url: &str) -> anyhow::Result<PgPool> {
     let pool = PgPoolOptions::new()
         .max_connections(20) // Increased for better concurrency
-        .min_connections(5) // Keep connections warm
-        .acquire_timeout(Duration::from_secs(30)) // Prevent hangs
+        .min_connections(min_connections) // Keep connections warm
+        .acquire_timeout(Duration::from_secs(ACQUIRE_TIMEOUT_SECS)) // Prevent hangs
         .idle_timeout(Duration::from_secs(600)) // 10 min idle timeout
         .max_lifetime(Duration::from_secs(1800)) // 30 min max lifetime
-        .connect(database_url)
+        .connect_with(options)
         .await
         .context("Failed to connect to database")?;
 
-    tracing::info!("✅ Database pool created: max=20, min=5");
+    tracing::info!(
+        "✅ Database pool created: max=20, min={}{}",
+        min_connections,
+        if config.db_pgbouncer_mode {
+            ", pgbouncer mode"
+        } else {
+            ""
+        }
+    );
+
+    // There are no minimum connections to warm through pgbouncer
+    if config.warm_pool && !config.db_pgbouncer_mode {
+        warm_pool(&pool).await?;
+    }
 
     Ok(pool)
 }
 
-pub async fn run_migrations(pool: &PgPool) -> anyhow::Result<()> {
-    sqlx::migrate!("./migrations")
+/// Opens the minimum connections now and runs `SELECT 1` on each, so a database
+/// that rejects connections fails startup instead of the first requests. They
+/// are all held at once to make sure they are distinct connections.
+async fn warm_pool(pool: &PgPool) -> anyhow::Result<()> {
+    let started = Instant::now();
+
+    let mut connections = Vec::with_capacity(MIN_CONNECTIONS as usize);
+    for _ in 0..MIN_CONNECTIONS {
+        let mut conn = pool
+            .acquire()
+            .await
+            .context("Failed to open a database connection while warming the pool")?;
+        sqlx::query("SELECT 1")
+            .execute(&mut *conn)
+            .await
+            .context("A new database connection failed its check while warming the pool")?;
+        connections.push(conn);
+    }
+    drop(connections);
+
+    tracing::info!(
+        "✅ Database pool warmed: {} connections checked in {:?}",
+        MIN_CONNECTIONS,
+        started.elapsed()
+    );
+
+    Ok(())
+}
+
+pub async fn run_migrations(pool: &PgPool, config: &Config) -> anyhow::Result<()> {
+    let mut migrator = sqlx::migrate!("./migrations");
+    // The migration lock is a session-level advisory lock. Through transaction
+    // pooling the unlock can reach a different server connection than the lock,
+    // leaving it held and the next deploy waiting forever
+    if config.db_pgbouncer_mode {
+        migrator.set_locking(false);
+    }
+
+    migrator
         .run(pool)
         .await
         .context("Failed to run database migrations")?;
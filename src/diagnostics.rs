@@ -0,0 +1,133 @@
+use anyhow::Context;
+use sqlx::{postgres::PgConnectOptions, PgPool};
+use std::net::SocketAddr;
+
+use crate::{
+    config::{jwt_secret_problem, Config, StorageBackend},
+    db::ACQUIRE_TIMEOUT_SECS,
+};
+
+/// One-time check run before the server starts accepting requests. Problems
+/// that would make every request fail stop startup; soft ones are warnings.
+/// Ends with a one-line summary of the effective setup for operators.
+pub async fn self_check(config: &Config, pool: &PgPool, addr: SocketAddr) -> anyhow::Result<()> {
+    sqlx::query("SELECT 1")
+        .execute(pool)
+        .await
+        .context("Self-check failed: the database is not reachable")?;
+
+    let known = sqlx::migrate!("./migrations").iter().count() as i64;
+    let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations WHERE success")
+        .fetch_one(pool)
+        .await
+        .context("Self-check failed: could not read the applied migrations")?;
+    if applied < known {
+        anyhow::bail!("Self-check failed: only {applied} of {known} migrations are applied");
+    }
+
+    // `Config::validate` already refused a weak secret in production
+    let jwt_secret = if config.jwt_secret_generated {
+        "generated"
+    } else if jwt_secret_problem(&config.jwt_secret).is_some() {
+        "weak"
+    } else {
+        "ok"
+    };
+
+    if config.maintenance_mode {
+        tracing::warn!("⚠️  Starting in maintenance mode; requests get 503 until it is turned off");
+    }
+    if config.db_pgbouncer_mode && config.items_notify_enabled {
+        tracing::warn!(
+            "⚠️  ITEMS_NOTIFY_ENABLED relies on LISTEN, which pgbouncer's transaction pooling \
+             doesn't support; item events may not reach other instances"
+        );
+    }
+    if !config.db_pgbouncer_mode && pgbouncer_port(&config.database_url) {
+        tracing::warn!(
+            "⚠️  DATABASE_URL uses port 6432, pgbouncer's default; set DB_PGBOUNCER_MODE=true \
+             if it pools transactions"
+        );
+    }
+    if std::iter::once(config.request_timeout_secs)
+        .chain(config.request_timeouts.iter().map(|&(_, secs)| secs))
+        .any(|secs| secs > 0 && secs <= ACQUIRE_TIMEOUT_SECS)
+    {
+        tracing::warn!(
+            "⚠️  A request timeout is no longer than the {}s database pool wait; requests \
+             starved of connections time out before the circuit breaker sees the failure",
+            ACQUIRE_TIMEOUT_SECS
+        );
+    }
+    if config.is_production() && !config.tls_enabled {
+        tracing::warn!(
+            "⚠️  TLS_ENABLED is off in production, so no Strict-Transport-Security is sent"
+        );
+    }
+
+    tracing::info!(
+        database = "reachable",
+        migrations = applied,
+        jwt_secret,
+        features = %enabled_features(config).join(", "),
+        listen = %addr,
+        "✅ Self-check passed"
+    );
+
+    Ok(())
+}
+
+fn pgbouncer_port(database_url: &str) -> bool {
+    database_url
+        .parse::<PgConnectOptions>()
+        .is_ok_and(|options| options.get_port() == 6432)
+}
+
+/// Names of the optional features this configuration turns on.
+fn enabled_features(config: &Config) -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if config.redis_url.is_some() {
+        features.push("redis");
+    }
+    if config.tls_enabled {
+        features.push("tls");
+    }
+    if config.force_https {
+        features.push("force-https");
+    }
+    if config.storage_backend == StorageBackend::S3 {
+        features.push("s3-storage");
+    }
+    if config.auth_cookie_enabled {
+        features.push("auth-cookie");
+    }
+    if config.workspaces_enabled {
+        features.push("workspaces");
+    }
+    if config.items_notify_enabled {
+        features.push("items-notify");
+    }
+    if config.single_flight_enabled {
+        features.push("single-flight");
+    }
+    if config.user_rate_limit > 0 {
+        features.push("user-rate-limit");
+    }
+    if config.cache_user_ttl_secs > 0 {
+        features.push("user-cache");
+    }
+    if config.db_breaker_threshold > 0 {
+        features.push("db-breaker");
+    }
+    if config.db_pgbouncer_mode {
+        features.push("pgbouncer");
+    }
+    if config.request_concurrency_limit > 0 {
+        features.push("load-shed");
+    }
+    if features.is_empty() {
+        features.push("none");
+    }
+
+    features
+}
@@ -1,28 +1,43 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use serde::Serialize;
 use serde_json::json;
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use crate::circuit_breaker::{is_connection_error, note_database_failure};
+
+/// Seconds clients are asked to wait when the connection pool is exhausted.
+const POOL_TIMEOUT_RETRY_AFTER_SECS: u64 = 2;
 
 #[derive(Debug, thiserror::Error)]
 pub enum AppError {
     #[error("Database error: {0}")]
-    Database(#[from] sqlx::Error),
+    Database(sqlx::Error),
     
     #[error("Authentication error: {0}")]
     Authentication(String),
     
+    /// Well-formed input that breaks a rule; `422`. Unparseable input is a `BadRequest`.
     #[error("Validation error: {0}")]
     Validation(String),
     
+    /// Like `Validation`, with the failing fields listed.
+    #[error("Validation error: {}", summarize_field_errors(.0))]
+    InvalidFields(Vec<FieldError>),
+    
+    /// Query parameters that don't parse (`?page=abc`); `400`, with the fields listed.
+    #[error("Invalid query parameters: {}", summarize_field_errors(.0))]
+    InvalidQuery(Vec<FieldError>),
+    
     #[error("Not found: {0}")]
     NotFound(String),
     
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
     
-    #[allow(dead_code)]
     #[error("Forbidden: {0}")]
     Forbidden(String),
     
@@ -31,6 +46,122 @@ pub enum AppError {
     
     #[error("Bad request: {0}")]
     BadRequest(String),
+    
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+    
+    #[error("Unprocessable entity: {0}")]
+    UnprocessableEntity(String),
+    
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    
+    /// An upload that would take a user past `MAX_STORAGE_BYTES_PER_USER`.
+    #[error("Storage quota exceeded: {usage_bytes} of {quota_bytes} bytes in use")]
+    StorageQuotaExceeded { usage_bytes: u64, quota_bytes: u64 },
+    
+    /// With the number of seconds to send as `Retry-After`, if known.
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String, Option<u64>),
+
+    /// With the number of seconds to send as `Retry-After`, if known.
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String, Option<u64>),
+}
+
+/// A single failed validation rule, addressed by its path in the request body.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    /// Dotted path, e.g. `title` or `tags[0].name`.
+    pub field: String,
+    /// JSON pointer to the same location, e.g. `/tags/0/name`.
+    pub pointer: String,
+    pub code: String,
+    pub message: String,
+}
+
+fn summarize_field_errors(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn collect_field_errors(
+    errors: &ValidationErrors,
+    field_prefix: &str,
+    pointer_prefix: &str,
+    out: &mut Vec<FieldError>,
+) {
+    for (name, kind) in errors.errors() {
+        let field = if field_prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", field_prefix, name)
+        };
+        let pointer = format!("{}/{}", pointer_prefix, name);
+
+        match kind {
+            ValidationErrorsKind::Field(field_errors) => {
+                out.extend(field_errors.iter().map(|e| FieldError {
+                    field: field.clone(),
+                    pointer: pointer.clone(),
+                    code: e.code.to_string(),
+                    message: e
+                        .message
+                        .as_ref()
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| format!("{} is invalid ({})", field, e.code)),
+                }));
+            }
+            ValidationErrorsKind::Struct(nested) => {
+                collect_field_errors(nested, &field, &pointer, out);
+            }
+            ValidationErrorsKind::List(items) => {
+                for (index, nested) in items {
+                    collect_field_errors(
+                        nested,
+                        &format!("{}[{}]", field, index),
+                        &format!("{}/{}", pointer, index),
+                        out,
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut details = Vec::new();
+        collect_field_errors(&errors, "", "", &mut details);
+        details.sort_by(|a, b| a.field.cmp(&b.field));
+        AppError::InvalidFields(details)
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        if is_connection_error(&e) {
+            note_database_failure();
+        }
+
+        match e {
+            // Pool exhaustion is transient overload, not a bug
+            sqlx::Error::PoolTimedOut => {
+                tracing::warn!("Database pool exhausted: connection acquire timed out");
+                AppError::ServiceUnavailable(
+                    "Server busy, retry shortly".to_string(),
+                    Some(POOL_TIMEOUT_RETRY_AFTER_SECS),
+                )
+            }
+            e => AppError::Database(e),
+        }
+    }
 }
 
 impl IntoResponse for AppError {
@@ -41,18 +172,58 @@ impl IntoResponse for AppError {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Database error occurred")
             }
             AppError::Authentication(_) => (StatusCode::UNAUTHORIZED, "Authentication failed"),
-            AppError::Validation(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
+            AppError::Validation(ref msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg.as_str()),
+            AppError::InvalidFields(_) => (StatusCode::UNPROCESSABLE_ENTITY, "Validation failed"),
+            AppError::InvalidQuery(_) => (StatusCode::BAD_REQUEST, "Invalid query parameters"),
             AppError::NotFound(ref msg) => (StatusCode::NOT_FOUND, msg.as_str()),
             AppError::Unauthorized(ref msg) => (StatusCode::UNAUTHORIZED, msg.as_str()),
             AppError::Forbidden(ref msg) => (StatusCode::FORBIDDEN, msg.as_str()),
             AppError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
             AppError::BadRequest(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
+            AppError::PayloadTooLarge(ref msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg.as_str()),
+            AppError::UnsupportedMediaType(ref msg) => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, msg.as_str())
+            }
+            AppError::UnprocessableEntity(ref msg) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, msg.as_str())
+            }
+            AppError::Conflict(ref msg) => (StatusCode::CONFLICT, msg.as_str()),
+            AppError::StorageQuotaExceeded { .. } => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "Storage quota exceeded")
+            }
+            AppError::ServiceUnavailable(ref msg, _) => {
+                (StatusCode::SERVICE_UNAVAILABLE, msg.as_str())
+            }
+            AppError::TooManyRequests(ref msg, _) => (StatusCode::TOO_MANY_REQUESTS, msg.as_str()),
+        };
+        let retry_after = match self {
+            AppError::ServiceUnavailable(_, retry_after)
+            | AppError::TooManyRequests(_, retry_after) => retry_after,
+            _ => None,
         };
 
-        let body = Json(json!({
+        let mut body = json!({
             "error": error_message,
             "message": self.to_string(),
-        }));
+        });
+        match self {
+            AppError::InvalidFields(ref details) | AppError::InvalidQuery(ref details) => {
+                body["details"] = json!(details)
+            }
+            AppError::StorageQuotaExceeded {
+                usage_bytes,
+                quota_bytes,
+            } => {
+                body["usage_bytes"] = json!(usage_bytes);
+                body["quota_bytes"] = json!(quota_bytes);
+            }
+            _ => {}
+        }
+        let body = Json(body);
+
+        if let Some(secs) = retry_after {
+            return (status, [(header::RETRY_AFTER, secs.to_string())], body).into_response();
+        }
 
         (status, body).into_response()
     }
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{models::Item, utils::timestamp, AppState};
+
+/// Postgres channel item mutations are announced on when `ITEMS_NOTIFY_ENABLED` is set.
+pub const ITEMS_CHANNEL: &str = "items_channel";
+
+/// How many events a slow subscriber may fall behind before it starts missing them.
+pub const ITEM_EVENTS_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A change to one item. Deliberately small: subscribers refetch what they need,
+/// and `NOTIFY` payloads are limited to 8000 bytes. `user_id` is whoever made the
+/// change; every member of `workspace_id` is told about it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemEvent {
+    pub kind: ItemEventKind,
+    pub item_id: Uuid,
+    pub workspace_id: Uuid,
+    pub user_id: Uuid,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub at: DateTime<Utc>,
+}
+
+impl ItemEvent {
+    pub fn new(kind: ItemEventKind, item: &Item, user_id: Uuid) -> Self {
+        Self {
+            kind,
+            item_id: item.id,
+            workspace_id: item.workspace_id,
+            user_id,
+            at: Utc::now(),
+        }
+    }
+}
+
+/// Announces an item change to subscribers. With `ITEMS_NOTIFY_ENABLED` the event goes
+/// through Postgres so every instance (including this one, via its listener) sees it;
+/// otherwise it is only broadcast in-process.
+///
+/// The mutation has already been committed, so failures are logged rather than returned.
+pub async fn publish_item_event(state: &AppState, event: ItemEvent) {
+    if !state.config.items_notify_enabled {
+        // No subscribers is not an error
+        let _ = state.item_events.send(event);
+        return;
+    }
+
+    // Listeners parse the payload back, so it can't follow the request's timestamp format
+    let payload = match timestamp::with_rfc3339(|| serde_json::to_string(&event)) {
+        Ok(payload) => payload,
+        Err(e) => {
+            tracing::error!("Failed to serialize item event: {:?}", e);
+            return;
+        }
+    };
+
+    if let Err(e) = sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(ITEMS_CHANNEL)
+        .bind(payload)
+        .execute(&state.db)
+        .await
+    {
+        tracing::error!("Failed to notify {}: {:?}", ITEMS_CHANNEL, e);
+    }
+}
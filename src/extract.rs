@@ -0,0 +1,533 @@
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{rejection::BytesRejection, FromRequest, FromRequestParts, MatchedPath, Request},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::convert::Infallible;
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, FieldError},
+    AppState,
+};
+
+/// `Content-Type` of MessagePack bodies, sent in responses and accepted in requests.
+pub const MSGPACK_CONTENT_TYPE: &str = "application/msgpack";
+
+/// Other names clients use for MessagePack.
+const MSGPACK_ALIASES: &[&str] = &["application/x-msgpack", "application/vnd.msgpack"];
+
+/// Drop-in replacement for `axum::Json` whose rejections use the `AppError` envelope
+/// and name the offending field and position instead of axum's plain-text 422.
+/// With `REJECT_UNKNOWN_FIELDS` set, fields the target type doesn't know are a 422.
+/// Bodies sent as `Content-Type: application/msgpack` are decoded as MessagePack.
+/// Routes with a schema in `JSON_SCHEMAS` have the body checked against it first.
+/// Bodies nested deeper than `JSON_MAX_DEPTH` are refused before being parsed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T> FromRequest<AppState> for Json<T>
+where
+    T: DeserializeOwned,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &AppState) -> Result<Self, Self::Rejection> {
+        let msgpack = msgpack_content_type(req.headers());
+        if !msgpack && !json_content_type(req.headers()) {
+            return Err(AppError::UnsupportedMediaType(format!(
+                "Expected request with `Content-Type: application/json` or `{}`",
+                MSGPACK_CONTENT_TYPE
+            )));
+        }
+
+        let route = req
+            .extensions()
+            .get::<MatchedPath>()
+            .map(|path| (req.method().to_string(), path.as_str().to_string()));
+
+        let bytes = Bytes::from_request(req, state).await.map_err(bytes_error)?;
+
+        // Deeply nested bodies are refused before any recursive parsing starts
+        let max_depth = state.config.json_max_depth;
+        if !msgpack && json_too_deep(&bytes, max_depth) {
+            return Err(too_deep(max_depth));
+        }
+
+        // Bodies that don't even parse are left to the typed decode below to report
+        if let Some((method, path)) = route.filter(|_| !state.json_schemas.is_empty()) {
+            let body: Option<serde_json::Value> = if msgpack {
+                let mut deserializer = rmp_serde::Deserializer::from_read_ref(&bytes);
+                deserializer.set_max_depth(max_depth + 1);
+                serde_json::Value::deserialize(&mut deserializer).ok()
+            } else {
+                serde_json::from_slice(&bytes).ok()
+            };
+            if let Some(body) = body {
+                state
+                    .json_schemas
+                    .check(&method, &path, &body)
+                    .map_err(AppError::InvalidFields)?;
+            }
+        }
+
+        let reject_unknown = state.config.reject_unknown_fields;
+        let (value, unknown_fields) = if msgpack {
+            let mut deserializer =
+                rmp_serde::Deserializer::from_read_ref(&bytes).with_human_readable();
+            // rmp_serde counts the limit itself as one level too deep
+            deserializer.set_max_depth(max_depth + 1);
+            decode(&mut deserializer, reject_unknown)
+                .map_err(|err| msgpack_error(err, max_depth))?
+        } else {
+            let deserializer = &mut serde_json::Deserializer::from_slice(&bytes);
+            decode(deserializer, reject_unknown).map_err(deserialize_error)?
+        };
+
+        if !unknown_fields.is_empty() {
+            return Err(AppError::InvalidFields(
+                unknown_fields
+                    .into_iter()
+                    .map(|field| FieldError {
+                        pointer: format!("/{}", field.replace('.', "/")),
+                        message: format!("Unknown field '{}'", field),
+                        code: "unknown_field".to_string(),
+                        field,
+                    })
+                    .collect(),
+            ));
+        }
+
+        Ok(Json(value))
+    }
+}
+
+/// Bodies over the `DefaultBodyLimit` stay `413`; other read failures are `400`.
+fn bytes_error(rejection: BytesRejection) -> AppError {
+    if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        AppError::PayloadTooLarge(rejection.body_text())
+    } else {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+/// Deserializes tracking the path to any error and, when `reject_unknown` is
+/// set, collecting the paths of fields `T` ignored.
+fn decode<'de, D, T>(
+    deserializer: D,
+    reject_unknown: bool,
+) -> Result<(T, Vec<String>), serde_path_to_error::Error<D::Error>>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let mut unknown_fields = Vec::new();
+    let value = if reject_unknown {
+        let mut record = |path: serde_ignored::Path| unknown_fields.push(path.to_string());
+        serde_path_to_error::deserialize(serde_ignored::Deserializer::new(
+            deserializer,
+            &mut record,
+        ))?
+    } else {
+        serde_path_to_error::deserialize(deserializer)?
+    };
+
+    Ok((value, unknown_fields))
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// `application/json` or any `application/*+json` type.
+fn json_content_type(headers: &HeaderMap) -> bool {
+    content_type(headers).is_some_and(|mime| {
+        mime.strip_prefix("application/")
+            .is_some_and(|subtype| subtype == "json" || subtype.ends_with("+json"))
+    })
+}
+
+fn msgpack_content_type(headers: &HeaderMap) -> bool {
+    content_type(headers).is_some_and(|mime| is_msgpack(&mime))
+}
+
+fn is_msgpack(mime: &str) -> bool {
+    mime == MSGPACK_CONTENT_TYPE || MSGPACK_ALIASES.contains(&mime)
+}
+
+/// The request's media type, lowercased and without parameters.
+fn content_type(headers: &HeaderMap) -> Option<String> {
+    let content_type = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    Some(
+        content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase(),
+    )
+}
+
+/// Whether `body` nests arrays and objects more than `max_depth` levels deep.
+/// Brackets inside strings don't count; malformed bodies are left to the parser.
+fn json_too_deep(body: &[u8], max_depth: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for &byte in body {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    false
+}
+
+fn too_deep(max_depth: usize) -> AppError {
+    AppError::BadRequest(format!(
+        "Request body is nested more than {} levels deep",
+        max_depth
+    ))
+}
+
+fn deserialize_error(err: serde_path_to_error::Error<serde_json::Error>) -> AppError {
+    let path = err.path().to_string();
+    let inner = err.into_inner();
+
+    match inner.classify() {
+        // Missing fields and the like are reported against the whole body (`.`)
+        serde_json::error::Category::Data if path != "." => {
+            AppError::Validation(format!("Invalid value for '{}': {}", path, inner))
+        }
+        serde_json::error::Category::Data => {
+            AppError::Validation(format!("Invalid request body: {}", inner))
+        }
+        _ => AppError::BadRequest(format!("Malformed JSON: {}", inner)),
+    }
+}
+
+fn msgpack_error(
+    err: serde_path_to_error::Error<rmp_serde::decode::Error>,
+    max_depth: usize,
+) -> AppError {
+    let path = err.path().to_string();
+    let inner = err.into_inner();
+
+    match inner {
+        rmp_serde::decode::Error::DepthLimitExceeded => too_deep(max_depth),
+        // A reserved marker is never valid MessagePack, so it is an encoding error
+        rmp_serde::decode::Error::TypeMismatch(rmp::Marker::Reserved) => {
+            AppError::BadRequest(format!("Malformed MessagePack: {}", inner))
+        }
+        // Type mismatches and custom (missing field, unknown variant) errors come
+        // from the data rather than the encoding
+        rmp_serde::decode::Error::TypeMismatch(_) | rmp_serde::decode::Error::Syntax(_)
+            if path != "." =>
+        {
+            AppError::Validation(format!("Invalid value for '{}': {}", path, inner))
+        }
+        rmp_serde::decode::Error::TypeMismatch(_) | rmp_serde::decode::Error::Syntax(_) => {
+            AppError::Validation(format!("Invalid request body: {}", inner))
+        }
+        _ => AppError::BadRequest(format!("Malformed MessagePack: {}", inner)),
+    }
+}
+
+/// Response body format picked from the request's `Accept` header. MessagePack
+/// is used when the client ranks it at least as high as JSON; otherwise JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    MessagePack,
+}
+
+impl ResponseFormat {
+    fn from_accept(headers: &HeaderMap) -> Self {
+        let mut json_q = 0.0_f32;
+        let mut msgpack_q = 0.0_f32;
+
+        let ranges = headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','));
+        for range in ranges {
+            let mut parts = range.split(';');
+            let mime = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let q = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if is_msgpack(&mime) {
+                msgpack_q = msgpack_q.max(q);
+            } else if matches!(mime.as_str(), "application/json" | "application/*" | "*/*") {
+                json_q = json_q.max(q);
+            }
+        }
+
+        if msgpack_q > 0.0 && msgpack_q >= json_q {
+            ResponseFormat::MessagePack
+        } else {
+            ResponseFormat::Json
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ResponseFormat
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ResponseFormat::from_accept(&parts.headers))
+    }
+}
+
+/// Response body serialized as JSON or MessagePack according to `ResponseFormat`.
+pub struct Negotiated<T>(pub ResponseFormat, pub T);
+
+impl<T: Serialize> IntoResponse for Negotiated<T> {
+    fn into_response(self) -> Response {
+        match self.0 {
+            ResponseFormat::Json => axum::Json(self.1).into_response(),
+            ResponseFormat::MessagePack => match encode_msgpack(&self.1) {
+                Ok(body) => ([(header::CONTENT_TYPE, MSGPACK_CONTENT_TYPE)], body).into_response(),
+                Err(e) => AppError::Internal(format!("Failed to encode MessagePack: {}", e))
+                    .into_response(),
+            },
+        }
+    }
+}
+
+/// Encodes structs as maps and UUIDs/timestamps as strings, matching the JSON shape.
+fn encode_msgpack<T: Serialize>(value: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    let mut body = Vec::new();
+    value.serialize(
+        &mut rmp_serde::Serializer::new(&mut body)
+            .with_struct_map()
+            .with_human_readable(),
+    )?;
+    Ok(body)
+}
+
+/// The authenticated user's id, whatever `JWT_SUBJECT` puts in the token. Set by
+/// `auth_middleware`; routes outside it reject with `401`.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser(pub Uuid);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .copied()
+            .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))
+    }
+}
+
+/// Replacement for `axum::extract::Query` that reports bad parameters as field
+/// errors in the `AppError` envelope instead of axum's plain-text 400. Parameters
+/// that don't parse stay a `400`; the target's `validate()` makes rule breaks a `422`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Query<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let deserializer =
+            serde_urlencoded::Deserializer::new(form_urlencoded::parse(query.as_bytes()));
+
+        serde_path_to_error::deserialize(deserializer)
+            .map(Query)
+            .map_err(|err| {
+                let field = err.path().to_string();
+                AppError::InvalidQuery(vec![FieldError {
+                    pointer: format!("/{}", field.replace('.', "/")),
+                    message: err.inner().to_string(),
+                    code: "invalid_query".to_string(),
+                    field,
+                }])
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        models::{ItemResponse, ListParams, ProjectedItem},
+        test_support,
+    };
+    use chrono::Utc;
+    use std::sync::Arc;
+    use validator::Validate;
+
+    fn item(title: &str) -> ItemResponse {
+        ItemResponse {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            workspace_id: Uuid::new_v4(),
+            title: title.to_string(),
+            description: None,
+            status: "todo".to_string(),
+            external_id: None,
+            tags: Vec::new(),
+            due_at: None,
+            archived_at: None,
+            position: 0,
+            completed_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            description_html: None,
+            rank: None,
+        }
+    }
+
+    async fn json_rejection(content_type: Option<&str>, body: Vec<u8>) -> AppError {
+        let config = test_support::config();
+        let db = test_support::stalled_pool(1, std::time::Duration::from_millis(50)).await;
+        let state = test_support::state(config, db).await;
+
+        let mut request = Request::post("/items");
+        if let Some(content_type) = content_type {
+            request = request.header(header::CONTENT_TYPE, content_type);
+        }
+        let request = request.body(axum::body::Body::from(body)).unwrap();
+
+        Json::<serde_json::Value>::from_request(request, &state)
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn missing_or_wrong_content_type_is_415() {
+        for content_type in [None, Some("text/plain")] {
+            let rejection = json_rejection(content_type, b"{}".to_vec()).await;
+
+            assert_eq!(
+                rejection.into_response().status(),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn body_over_the_default_limit_is_413() {
+        // axum's default body limit is 2 MB
+        let body = vec![b' '; 2 * 1024 * 1024 + 1];
+        let rejection = json_rejection(Some("application/json"), body).await;
+
+        assert_eq!(
+            rejection.into_response().status(),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    async fn list_params(query: &str) -> Result<ListParams, AppError> {
+        let request = Request::get(format!("/items?{}", query)).body(()).unwrap();
+        let (mut parts, _) = request.into_parts();
+        let Query(params) = Query::<ListParams>::from_request_parts(&mut parts, &()).await?;
+        params.validate()?;
+
+        Ok(params)
+    }
+
+    async fn status_and_details(error: AppError) -> (StatusCode, serde_json::Value) {
+        let response = error.into_response();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), 4096)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        (status, body["details"].clone())
+    }
+
+    #[tokio::test]
+    async fn unparseable_query_values_are_400_with_field_details() {
+        let (status, details) =
+            status_and_details(list_params("page=abc").await.unwrap_err()).await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(details[0]["field"], "page");
+        assert_eq!(details[0]["code"], "invalid_query");
+    }
+
+    #[tokio::test]
+    async fn query_values_breaking_a_rule_are_422_with_field_details() {
+        let (status, details) =
+            status_and_details(list_params("per_page=500").await.unwrap_err()).await;
+
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(details[0]["field"], "per_page");
+    }
+
+    #[test]
+    fn projected_items_round_trip_through_msgpack_when_requested_fields_are_absent() {
+        // `rank` and `description_html` are only present when searching or rendering
+        let fields: Arc<[&'static str]> =
+            Arc::from(vec!["id", "title", "description_html", "rank"]);
+        let items: Vec<ProjectedItem> = ["first", "second"]
+            .into_iter()
+            .map(|title| ProjectedItem {
+                item: item(title),
+                fields: Some(fields.clone()),
+            })
+            .collect();
+
+        let body = encode_msgpack(&items).unwrap();
+        let decoded: serde_json::Value = rmp_serde::from_slice(&body).unwrap();
+
+        let titles: Vec<_> = decoded
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| {
+                assert_eq!(item.as_object().unwrap().len(), 2);
+                item["title"].as_str().unwrap()
+            })
+            .collect();
+        assert_eq!(titles, ["first", "second"]);
+    }
+}
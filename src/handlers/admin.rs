@@ -0,0 +1,293 @@
+use axum::{
+    extract::{OriginalUri, Path, State},
+    http::StatusCode,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::atomic::Ordering};
+use unicode_normalization::UnicodeNormalization;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::{AppError, AppResult},
+    extract::{AuthUser, Json, Query},
+    handlers::escape_like,
+    models::{
+        AdminUpdateUser, AdminUserFilter, CreateInvite, Invite, InviteResponse, ListParams, Page,
+        PageLinks, User, UserResponse, DEFAULT_USER_SORT, ROLE_ADMIN, USER_ROLES, USER_SORT_FIELDS,
+    },
+    utils::auth::{generate_token, hash_token},
+    AppState,
+};
+
+const INVITE_TOKEN_LEN: usize = 40;
+
+/// `GET /admin/stats` results are reused for this long, across instances with Redis.
+const ADMIN_STATS_TTL: std::time::Duration = std::time::Duration::from_secs(30);
+const ADMIN_STATS_KEY: &str = "admin:stats";
+
+#[derive(Debug, Deserialize)]
+pub struct SetMaintenance {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+}
+
+pub async fn get_maintenance(State(state): State<AppState>) -> Json<MaintenanceStatus> {
+    Json(MaintenanceStatus {
+        enabled: state.maintenance.load(Ordering::Relaxed),
+    })
+}
+
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Json(payload): Json<SetMaintenance>,
+) -> AppResult<Json<MaintenanceStatus>> {
+    state.maintenance.store(payload.enabled, Ordering::Relaxed);
+
+    tracing::warn!(
+        "Maintenance mode {}",
+        if payload.enabled { "enabled" } else { "disabled" }
+    );
+
+    Ok(Json(MaintenanceStatus {
+        enabled: payload.enabled,
+    }))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminStats {
+    pub total_users: i64,
+    pub total_items: i64,
+    pub items_by_status: BTreeMap<String, i64>,
+    pub signups_last_24h: i64,
+    pub signups_last_7d: i64,
+    /// When the counts were taken; they can be up to 30 seconds old.
+    pub generated_at: DateTime<Utc>,
+}
+
+/// User and item counts for the admin dashboard.
+pub async fn get_stats(State(state): State<AppState>) -> AppResult<Json<AdminStats>> {
+    if let Ok(Some(cached)) = state.kv.get(ADMIN_STATS_KEY).await {
+        if let Ok(stats) = serde_json::from_slice(&cached) {
+            return Ok(Json(stats));
+        }
+    }
+
+    let (total_users, signups_last_24h, signups_last_7d): (i64, i64, i64) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*),
+               COUNT(*) FILTER (WHERE created_at > NOW() - INTERVAL '24 hours'),
+               COUNT(*) FILTER (WHERE created_at > NOW() - INTERVAL '7 days')
+        FROM users
+        "#,
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    let items_by_status: BTreeMap<String, i64> =
+        sqlx::query_as::<_, (String, i64)>("SELECT status, COUNT(*) FROM items GROUP BY status")
+            .fetch_all(&state.db)
+            .await?
+            .into_iter()
+            .collect();
+
+    let stats = AdminStats {
+        total_users,
+        total_items: items_by_status.values().sum(),
+        items_by_status,
+        signups_last_24h,
+        signups_last_7d,
+        generated_at: Utc::now(),
+    };
+
+    if let Ok(value) = serde_json::to_vec(&stats) {
+        if let Err(e) = state.kv.set(ADMIN_STATS_KEY, &value, ADMIN_STATS_TTL).await {
+            tracing::warn!("Failed to cache admin stats: {}", e);
+        }
+    }
+
+    Ok(Json(stats))
+}
+
+pub async fn create_invite(
+    State(state): State<AppState>,
+    AuthUser(admin_uuid): AuthUser,
+    Json(payload): Json<CreateInvite>,
+) -> AppResult<(StatusCode, Json<InviteResponse>)> {
+    // Validate input
+    payload.validate()?;
+
+    let expires_at = payload
+        .expires_at
+        .unwrap_or_else(|| Utc::now() + Duration::seconds(state.config.invite_expiration));
+    if expires_at <= Utc::now() {
+        return Err(AppError::Validation(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+
+    let token = generate_token(INVITE_TOKEN_LEN);
+
+    let invite = sqlx::query_as::<_, Invite>(
+        r#"
+        INSERT INTO invites (token_hash, email, created_by, expires_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, email, expires_at, created_at
+        "#,
+    )
+    .bind(hash_token(&token))
+    .bind(&payload.email)
+    .bind(admin_uuid)
+    .bind(expires_at)
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(InviteResponse {
+            id: invite.id,
+            token,
+            email: invite.email,
+            expires_at: invite.expires_at,
+            created_at: invite.created_at,
+        }),
+    ))
+}
+
+/// One page of users, optionally narrowed by `?q=`, `?role=` and `?is_active=`.
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+    Query(filter): Query<AdminUserFilter>,
+    OriginalUri(uri): OriginalUri,
+) -> AppResult<Json<Page<UserResponse>>> {
+    params.validate()?;
+    filter.validate()?;
+    if let Some(role) = &filter.role {
+        validate_user_role(role)?;
+    }
+
+    let order_by = params.order_by(USER_SORT_FIELDS, DEFAULT_USER_SORT)?;
+    // Usernames are stored NFC-normalized, so the search text is too
+    let search = filter
+        .q
+        .as_deref()
+        .map(|q| format!("%{}%", escape_like(&q.nfc().collect::<String>())));
+
+    const USER_FILTER: &str = "($1::TEXT IS NULL OR email ILIKE $1 OR username ILIKE $1) \
+         AND ($2::TEXT IS NULL OR role = $2) \
+         AND ($3::BOOLEAN IS NULL OR is_active = $3)";
+
+    let total: i64 =
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM users WHERE {}", USER_FILTER))
+            .bind(&search)
+            .bind(&filter.role)
+            .bind(filter.is_active)
+            .fetch_one(&state.db)
+            .await?;
+
+    // `order_by` only ever contains allowlisted columns; `id` keeps pages stable on ties
+    let users = sqlx::query_as::<_, User>(&format!(
+        "SELECT * FROM users WHERE {} ORDER BY {}, id LIMIT $4 OFFSET $5",
+        USER_FILTER, order_by
+    ))
+    .bind(&search)
+    .bind(&filter.role)
+    .bind(filter.is_active)
+    .bind(params.per_page)
+    .bind(params.offset())
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(Page {
+        data: users.into_iter().map(UserResponse::from).collect(),
+        page: params.page,
+        per_page: params.per_page,
+        total,
+        links: PageLinks::new(&uri, &params, total),
+    }))
+}
+
+/// Changes a user's role or active status. The last active admin can't be
+/// demoted or deactivated, so there is always someone left to undo mistakes.
+/// Deactivating here also locks the account, so the user can't reactivate it
+/// themselves; activating it again clears the lock.
+pub async fn update_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<AdminUpdateUser>,
+) -> AppResult<Json<UserResponse>> {
+    if let Some(role) = &payload.role {
+        validate_user_role(role)?;
+    }
+
+    let mut tx = state.db.begin().await?;
+    let loses_admin = matches!(payload.role.as_deref(), Some(role) if role != ROLE_ADMIN)
+        || payload.is_active == Some(false);
+    if loses_admin {
+        ensure_other_admin(&mut tx, user_id).await?;
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users
+        SET role = COALESCE($2, role),
+            is_active = COALESCE($3, is_active),
+            locked_at = CASE
+                WHEN $3 IS NULL THEN locked_at
+                WHEN $3 THEN NULL
+                ELSE COALESCE(locked_at, CURRENT_TIMESTAMP)
+            END
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(user_id)
+    .bind(&payload.role)
+    .bind(payload.is_active)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    tx.commit().await?;
+    state.user_cache.invalidate(user_id).await;
+
+    Ok(Json(user.into()))
+}
+
+/// Fails with 409 when `user_id` is the only active admin. Locks the admin rows
+/// so two admins can't demote each other at the same time.
+pub(crate) async fn ensure_other_admin(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    user_id: Uuid,
+) -> AppResult<()> {
+    let admins: Vec<Uuid> =
+        sqlx::query_scalar("SELECT id FROM users WHERE role = $1 AND is_active FOR UPDATE")
+            .bind(ROLE_ADMIN)
+            .fetch_all(&mut **tx)
+            .await?;
+
+    if admins == [user_id] {
+        return Err(AppError::Conflict(
+            "The last active admin can't be demoted or deactivated".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_user_role(role: &str) -> AppResult<()> {
+    if !USER_ROLES.contains(&role) {
+        return Err(AppError::Validation(format!(
+            "Unknown role '{}'. Allowed roles: {}",
+            role,
+            USER_ROLES.join(", ")
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::{AppError, AppResult},
+    extract::{AuthUser, Json},
+    models::{ApiKey, ApiKeyResponse, CreateApiKey, CreatedApiKeyResponse},
+    utils::auth::{all_scopes, generate_api_key, hash_token, ALL_SCOPES},
+    AppState,
+};
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Json(payload): Json<CreateApiKey>,
+) -> AppResult<(StatusCode, Json<CreatedApiKeyResponse>)> {
+    // Validate input
+    payload.validate()?;
+
+    if payload.expires_at.is_some_and(|at| at <= chrono::Utc::now()) {
+        return Err(AppError::Validation(
+            "expires_at must be in the future".to_string(),
+        ));
+    }
+
+    // Keys get every scope unless the caller narrows them down
+    let scopes = payload.scopes.unwrap_or_else(all_scopes);
+    if let Some(unknown) = scopes.iter().find(|s| !ALL_SCOPES.contains(&s.as_str())) {
+        return Err(AppError::Validation(format!(
+            "Unknown scope '{}'. Allowed scopes: {}",
+            unknown,
+            ALL_SCOPES.join(", ")
+        )));
+    }
+
+    let (key, prefix) = generate_api_key();
+
+    let api_key = sqlx::query_as::<_, ApiKey>(
+        r#"
+        INSERT INTO api_keys (user_id, label, key_prefix, key_hash, scopes, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING *
+        "#,
+    )
+    .bind(user_uuid)
+    .bind(&payload.label)
+    .bind(&prefix)
+    .bind(hash_token(&key))
+    .bind(&scopes)
+    .bind(payload.expires_at)
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiKeyResponse {
+            key,
+            api_key: api_key.into(),
+        }),
+    ))
+}
+
+pub async fn get_api_keys(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+) -> AppResult<Json<Vec<ApiKeyResponse>>> {
+    let keys = sqlx::query_as::<_, ApiKey>(
+        "SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(user_uuid)
+    .fetch_all(&state.db)
+    .await?;
+
+    let responses: Vec<ApiKeyResponse> = keys.into_iter().map(Into::into).collect();
+    Ok(Json(responses))
+}
+
+pub async fn delete_api_key(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(key_id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    let result = sqlx::query("DELETE FROM api_keys WHERE id = $1 AND user_id = $2")
+        .bind(key_id)
+        .bind(user_uuid)
+        .execute(&state.db)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("API key not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,379 @@
+use axum::{
+    body::Body,
+    extract::{multipart::Field, Multipart, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::{Arc, Mutex};
+use tokio_stream::StreamExt;
+use tokio_util::io::{ReaderStream, StreamReader};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    extract::{AuthUser, Json},
+    handlers::{item_access, require_item_write},
+    models::{sanitize_attachment_filename, Attachment, AttachmentResponse},
+    storage::{Storage, StorageError},
+    AppState,
+};
+
+/// Multipart field that carries the uploaded file.
+const ATTACHMENT_FIELD: &str = "file";
+
+/// Room for multipart boundaries and part headers on top of the file itself
+/// when sizing the request body limit of the upload route.
+pub const MULTIPART_OVERHEAD_BYTES: usize = 64 * 1024;
+
+/// Uploads a file to an item as `multipart/form-data` (field `file`). The body
+/// is streamed to storage rather than buffered in memory.
+pub async fn upload_attachment(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(item_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> AppResult<(StatusCode, Json<AttachmentResponse>)> {
+    let (_, role) = item_access(&state, user_uuid, item_id).await?;
+    require_item_write(&role)?;
+
+    while let Some(field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() == Some(ATTACHMENT_FIELD) {
+            let attachment = store_attachment(&state, user_uuid, item_id, field).await?;
+            return Ok((
+                StatusCode::CREATED,
+                Json(attachment_response(&state, attachment).await),
+            ));
+        }
+    }
+
+    Err(AppError::BadRequest(format!(
+        "Missing multipart field '{}'",
+        ATTACHMENT_FIELD
+    )))
+}
+
+pub async fn get_attachments(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(item_id): Path<Uuid>,
+) -> AppResult<Json<Vec<AttachmentResponse>>> {
+    item_access(&state, user_uuid, item_id).await?;
+
+    let attachments = sqlx::query_as::<_, Attachment>(
+        "SELECT * FROM attachments WHERE item_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(item_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut responses = Vec::with_capacity(attachments.len());
+    for attachment in attachments {
+        responses.push(attachment_response(&state, attachment).await);
+    }
+
+    Ok(Json(responses))
+}
+
+/// Streams an attachment back with its original content type and filename.
+pub async fn download_attachment(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path((item_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<Response> {
+    item_access(&state, user_uuid, item_id).await?;
+
+    let attachment =
+        sqlx::query_as::<_, Attachment>("SELECT * FROM attachments WHERE id = $1 AND item_id = $2")
+            .bind(attachment_id)
+            .bind(item_id)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Attachment not found".to_string()))?;
+
+    let reader = match state.storage.get(&attachment.storage_key).await {
+        Ok(reader) => reader,
+        Err(StorageError::NotFound(_)) => {
+            tracing::error!(
+                "Attachment {} has no stored blob at '{}'",
+                attachment.id,
+                attachment.storage_key
+            );
+            return Err(AppError::NotFound("Attachment not found".to_string()));
+        }
+        Err(e) => return Err(storage_error(e)),
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, attachment.content_type),
+            (header::CONTENT_LENGTH, attachment.size_bytes.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                content_disposition(&attachment.filename),
+            ),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response())
+}
+
+/// Storage keys of every attachment on the given items, collected before the
+/// items are deleted so the blobs can be removed once the rows are gone.
+pub(crate) async fn attachment_keys(state: &AppState, item_ids: &[Uuid]) -> AppResult<Vec<String>> {
+    let keys = sqlx::query_scalar("SELECT storage_key FROM attachments WHERE item_id = ANY($1)")
+        .bind(item_ids)
+        .fetch_all(&state.db)
+        .await?;
+
+    Ok(keys)
+}
+
+/// Removes stored blobs whose rows are already gone. Failures are logged, not
+/// returned: the owning delete has succeeded by the time this runs.
+pub(crate) async fn remove_attachment_blobs(state: &AppState, keys: Vec<String>) {
+    for key in keys {
+        if let Err(e) = state.storage.delete(&key).await {
+            tracing::warn!("Failed to remove attachment blob '{}': {}", key, e);
+        }
+    }
+}
+
+/// Checks the part's content type against the allow-list, writes it to
+/// storage and records it. The blob is removed again if anything fails.
+async fn store_attachment(
+    state: &AppState,
+    user_uuid: Uuid,
+    item_id: Uuid,
+    field: Field<'_>,
+) -> AppResult<Attachment> {
+    let content_type = field
+        .content_type()
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    if !state
+        .config
+        .attachment_content_types
+        .contains(&content_type)
+    {
+        return Err(AppError::UnsupportedMediaType(format!(
+            "Content type '{}' is not allowed. Allowed types: {}",
+            content_type,
+            state.config.attachment_content_types.join(", ")
+        )));
+    }
+
+    let filename = sanitize_attachment_filename(field.file_name());
+    let attachment_id = Uuid::new_v4();
+    let storage_key = attachment_id.to_string();
+
+    // Uploads that can't fit in what is left of the quota are cut off while
+    // streaming; the total is checked again when the row is written
+    let quota_bytes = state.config.max_storage_bytes_per_user;
+    let usage_bytes = if quota_bytes > 0 {
+        storage_usage(&state.db, user_uuid).await?
+    } else {
+        0
+    };
+    if quota_bytes > 0 && usage_bytes >= quota_bytes {
+        return Err(AppError::StorageQuotaExceeded {
+            usage_bytes,
+            quota_bytes,
+        });
+    }
+    let remaining_bytes = match quota_bytes {
+        0 => u64::MAX,
+        quota_bytes => quota_bytes - usage_bytes,
+    };
+
+    // Count bytes as they stream through so oversized uploads stop early
+    let max_bytes = state.config.attachment_max_bytes;
+    let upload_error = Mutex::new(None);
+    let mut received: u64 = 0;
+    let chunks = field.map(|chunk| {
+        let chunk = chunk.map_err(|e| abort_upload(&upload_error, multipart_error(e)))?;
+        received += chunk.len() as u64;
+        if received > max_bytes {
+            return Err(abort_upload(&upload_error, attachment_too_large(max_bytes)));
+        }
+        if received > remaining_bytes {
+            let exceeded = AppError::StorageQuotaExceeded {
+                usage_bytes,
+                quota_bytes,
+            };
+            return Err(abort_upload(&upload_error, exceeded));
+        }
+        Ok(chunk)
+    });
+    let mut reader = StreamReader::new(chunks);
+
+    let unsaved = UnsavedBlob {
+        storage: state.storage.clone(),
+        key: storage_key.clone(),
+    };
+    let stored = state
+        .storage
+        .put(&storage_key, &content_type, &mut reader)
+        .await;
+    drop(reader);
+    let size_bytes = match stored {
+        Ok(size_bytes) => size_bytes,
+        Err(e) => {
+            let aborted = upload_error.into_inner().unwrap_or_else(|e| e.into_inner());
+            return Err(aborted.unwrap_or_else(|| storage_error(e)));
+        }
+    };
+
+    let inserted: AppResult<Attachment> = async {
+        let mut tx = state.db.begin().await?;
+        ensure_storage_quota(&mut tx, state, user_uuid, size_bytes).await?;
+        let attachment = sqlx::query_as::<_, Attachment>(
+            r#"
+            INSERT INTO attachments
+                (id, item_id, user_id, filename, content_type, size_bytes, storage_key)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(attachment_id)
+        .bind(item_id)
+        .bind(user_uuid)
+        .bind(&filename)
+        .bind(&content_type)
+        .bind(size_bytes as i64)
+        .bind(&storage_key)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(attachment)
+    }
+    .await;
+
+    if inserted.is_ok() {
+        std::mem::forget(unsaved);
+    }
+    inserted
+}
+
+/// A stored blob that no attachment row refers to yet. Deleted when dropped, so
+/// failed uploads, and uploads cancelled halfway (the client went away or the
+/// route timed out), leave nothing behind.
+struct UnsavedBlob {
+    storage: Arc<dyn Storage>,
+    key: String,
+}
+
+impl Drop for UnsavedBlob {
+    fn drop(&mut self) {
+        let storage = self.storage.clone();
+        let key = std::mem::take(&mut self.key);
+        tokio::spawn(async move {
+            if let Err(e) = storage.delete(&key).await {
+                tracing::warn!("Failed to delete unsaved attachment blob '{}': {}", key, e);
+            }
+        });
+    }
+}
+
+/// Fails when storing `size_bytes` more would take the user past their quota.
+/// Locks the user's row so concurrent uploads can't overshoot it together.
+async fn ensure_storage_quota(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    state: &AppState,
+    user_uuid: Uuid,
+    size_bytes: u64,
+) -> AppResult<()> {
+    let quota_bytes = state.config.max_storage_bytes_per_user;
+    if quota_bytes == 0 {
+        return Ok(());
+    }
+
+    sqlx::query("SELECT 1 FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_uuid)
+        .execute(&mut **tx)
+        .await?;
+    let usage_bytes = storage_usage(&mut **tx, user_uuid).await?;
+    if usage_bytes + size_bytes > quota_bytes {
+        return Err(AppError::StorageQuotaExceeded {
+            usage_bytes,
+            quota_bytes,
+        });
+    }
+
+    Ok(())
+}
+
+/// Total bytes of the attachments a user has uploaded.
+async fn storage_usage<'e>(executor: impl sqlx::PgExecutor<'e>, user_uuid: Uuid) -> AppResult<u64> {
+    let usage: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(size_bytes), 0)::BIGINT FROM attachments WHERE user_id = $1",
+    )
+    .bind(user_uuid)
+    .fetch_one(executor)
+    .await?;
+
+    Ok(usage as u64)
+}
+
+/// Records why an upload stream was cut short, so the storage backend's
+/// generic I/O error can be reported as the real cause.
+fn abort_upload(slot: &Mutex<Option<AppError>>, error: AppError) -> std::io::Error {
+    *slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(error);
+    std::io::Error::other("upload aborted")
+}
+
+/// Attachment metadata, with a direct download link when the storage
+/// backend can presign one.
+async fn attachment_response(state: &AppState, attachment: Attachment) -> AttachmentResponse {
+    let download_url = match state
+        .storage
+        .presigned_url(
+            &attachment.storage_key,
+            state.config.storage_presign_expiry_secs,
+        )
+        .await
+    {
+        Ok(url) => url,
+        Err(e) => {
+            tracing::warn!("Failed to presign attachment {}: {}", attachment.id, e);
+            None
+        }
+    };
+
+    AttachmentResponse {
+        download_url,
+        ..attachment.into()
+    }
+}
+
+fn content_disposition(filename: &str) -> String {
+    // Header values must be visible ASCII; anything else is replaced
+    let ascii: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("attachment; filename=\"{}\"", ascii)
+}
+
+fn attachment_too_large(max_bytes: u64) -> AppError {
+    AppError::PayloadTooLarge(format!("Attachments may be at most {} bytes", max_bytes))
+}
+
+fn multipart_error(e: axum::extract::multipart::MultipartError) -> AppError {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        AppError::PayloadTooLarge(e.body_text())
+    } else {
+        AppError::BadRequest(e.body_text())
+    }
+}
+
+fn storage_error(e: StorageError) -> AppError {
+    tracing::error!("Attachment storage error: {}", e);
+    AppError::Internal("Attachment storage error".to_string())
+}
@@ -1,30 +1,68 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+};
+use chrono::{Duration, Utc};
+use std::net::SocketAddr;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
-    error::{AppError, AppResult},
-    models::{AuthResponse, CreateUser, LoginUser, User, UserResponse},
-    utils::auth::{create_token, hash_password, verify_password},
+    config::SignupMode,
+    error::{AppError, AppResult, FieldError},
+    extract::{AuthUser, Json, Query},
+    handlers::ensure_other_admin,
+    middleware::check_account,
+    models::{
+        is_allowed_sort, AuthResponse, ChangeEmail, CreateUser, EmailChangeResponse,
+        IntrospectRequest, IntrospectionResponse, LoginUser, MeQuery, MeResponse,
+        TokenVerification, UpdateProfile, User, UserPreferences, UserResponse, UserStats,
+        VerifyEmailChange, EMAIL_CHANGE_EXPIRATION_HOURS, ITEM_SORT_FIELDS,
+    },
+    utils::{
+        auth::{
+            create_token, generate_token, hash_password, hash_token, needs_rehash, verify_password,
+            verify_token,
+        },
+        cookie::AuthCookie,
+        username::normalize_username,
+    },
     AppState,
 };
 
+const EMAIL_CHANGE_TOKEN_LEN: usize = 40;
+
 pub async fn signup(
     State(state): State<AppState>,
-    Json(payload): Json<CreateUser>,
-) -> AppResult<(StatusCode, Json<AuthResponse>)> {
+    headers: HeaderMap,
+    Json(mut payload): Json<CreateUser>,
+) -> AppResult<(StatusCode, AuthCookie, Json<AuthResponse>)> {
+    if state.config.signup_mode == SignupMode::Closed {
+        return Err(AppError::Forbidden("Signups are disabled".to_string()));
+    }
+
+    // Normalize before validating so the length rules apply to what gets stored
+    payload.username = normalize_username(
+        &payload.username,
+        state.config.username_charset,
+        state.config.username_case_fold,
+    )
+    .map_err(AppError::Validation)?;
+
     // Validate input
-    payload
-        .validate()
-        .map_err(|e| AppError::Validation(e.to_string()))?;
-
-    // Check if user already exists
-    let existing_user =
-        sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1 OR username = $2")
-            .bind(&payload.email)
-            .bind(&payload.username)
-            .fetch_optional(&state.db)
-            .await?;
+    payload.validate()?;
+
+    // Check if user already exists; with case folding, accounts from before it
+    // was enabled may still have uppercase names
+    let existing_user = sqlx::query_as::<_, User>(
+        "SELECT * FROM users \
+         WHERE email = $1 OR username = $2 OR ($3 AND LOWER(username) = $2)",
+    )
+    .bind(&payload.email)
+    .bind(&payload.username)
+    .bind(state.config.username_case_fold)
+    .fetch_optional(&state.db)
+    .await?;
 
     if existing_user.is_some() {
         return Err(AppError::BadRequest(
@@ -33,9 +71,41 @@ pub async fn signup(
     }
 
     // Hash password
-    let password_hash = hash_password(&payload.password)
+    let password_hash = hash_password(&payload.password, state.config.bcrypt_cost)
+        .await
         .map_err(|e| AppError::Internal(format!("Failed to hash password: {}", e)))?;
 
+    let mut tx = state.db.begin().await?;
+
+    // Consume the invite atomically so it can't be used twice
+    let invite_id = if state.config.signup_mode == SignupMode::InviteOnly {
+        let token = payload.invite_token.as_deref().ok_or_else(|| {
+            AppError::Forbidden("An invite token is required to sign up".to_string())
+        })?;
+
+        let (invite_id, invite_email): (Uuid, Option<String>) = sqlx::query_as(
+            r#"
+            UPDATE invites SET used_at = NOW()
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING id, email
+            "#,
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::Forbidden("Invalid or expired invite token".to_string()))?;
+
+        if invite_email.is_some_and(|email| !email.eq_ignore_ascii_case(&payload.email)) {
+            return Err(AppError::Forbidden(
+                "This invite was issued for a different email address".to_string(),
+            ));
+        }
+
+        Some(invite_id)
+    } else {
+        None
+    };
+
     // Create user
     let user = sqlx::query_as::<_, User>(
         "INSERT INTO users (email, username, password_hash) VALUES ($1, $2, $3) RETURNING *",
@@ -43,15 +113,26 @@ pub async fn signup(
     .bind(&payload.email)
     .bind(&payload.username)
     .bind(&password_hash)
-    .fetch_one(&state.db)
+    .fetch_one(&mut *tx)
     .await?;
 
+    if let Some(invite_id) = invite_id {
+        sqlx::query("UPDATE invites SET used_by = $1 WHERE id = $2")
+            .bind(user.id)
+            .bind(invite_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+
     // Generate JWT token
-    let token = create_token(user.id, user.email.clone(), &state.config)
+    let token = create_token(&user, &state.config, &headers)
         .map_err(|e| AppError::Internal(format!("Failed to create token: {}", e)))?;
 
     Ok((
         StatusCode::CREATED,
+        AuthCookie::issue(&state.config, &token),
         Json(AuthResponse {
             token,
             user: user.into(),
@@ -61,12 +142,12 @@ pub async fn signup(
 
 pub async fn login(
     State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<LoginUser>,
-) -> AppResult<Json<AuthResponse>> {
+) -> AppResult<(AuthCookie, Json<AuthResponse>)> {
     // Validate input
-    payload
-        .validate()
-        .map_err(|e| AppError::Validation(e.to_string()))?;
+    payload.validate()?;
 
     // Find user by email
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
@@ -77,6 +158,7 @@ pub async fn login(
 
     // Verify password
     let is_valid = verify_password(&payload.password, &user.password_hash)
+        .await
         .map_err(|e| AppError::Internal(format!("Failed to verify password: {}", e)))?;
 
     if !is_valid {
@@ -85,23 +167,163 @@ pub async fn login(
         ));
     }
 
+    ensure_can_log_in(&user)?;
+
+    upgrade_password_hash(&state, &user, &payload.password).await;
+    let user = record_login(&state, user.id, addr).await?;
+
     // Generate JWT token
-    let token = create_token(user.id, user.email.clone(), &state.config)
+    let token = create_token(&user, &state.config, &headers)
         .map_err(|e| AppError::Internal(format!("Failed to create token: {}", e)))?;
 
-    Ok(Json(AuthResponse {
-        token,
+    Ok((
+        AuthCookie::issue(&state.config, &token),
+        Json(AuthResponse {
+            token,
+            user: user.into(),
+        }),
+    ))
+}
+
+/// Clears the auth cookie. Bearer tokens are stateless; use
+/// `POST /auth/invalidate-tokens` to revoke them.
+pub async fn logout(State(state): State<AppState>) -> (AuthCookie, StatusCode) {
+    (AuthCookie::clear(&state.config), StatusCode::NO_CONTENT)
+}
+
+pub async fn get_me(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Query(query): Query<MeQuery>,
+) -> AppResult<Json<MeResponse>> {
+    let user = match state.user_cache.get(user_uuid).await {
+        Some(user) => user,
+        None => {
+            let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+                .bind(user_uuid)
+                .fetch_optional(&state.db)
+                .await?
+                .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+            state.user_cache.insert(&user).await;
+            user
+        }
+    };
+
+    // Item statistics are opt-in to keep the plain profile fetch cheap
+    let stats = if query.stats {
+        let counts: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT status, COUNT(*) FROM items WHERE user_id = $1 GROUP BY status",
+        )
+        .bind(user_uuid)
+        .fetch_all(&state.db)
+        .await?;
+
+        Some(UserStats {
+            total_items: counts.iter().map(|(_, count)| count).sum(),
+            items_by_status: counts.into_iter().collect(),
+        })
+    } else {
+        None
+    };
+
+    Ok(Json(MeResponse {
         user: user.into(),
+        stats,
     }))
 }
 
-pub async fn get_me(
+/// Changes the caller's display name, avatar and bio. Omitted fields are kept;
+/// `null` or a blank string clears them.
+pub async fn update_profile(
     State(state): State<AppState>,
-    user_id: axum::Extension<String>,
+    AuthUser(user_uuid): AuthUser,
+    Json(mut payload): Json<UpdateProfile>,
 ) -> AppResult<Json<UserResponse>> {
-    let user_uuid: Uuid = user_id
-        .parse()
-        .map_err(|_| AppError::Internal("Invalid user ID format".to_string()))?;
+    payload.normalize();
+    payload.validate()?;
+
+    let user = sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users SET
+            display_name = CASE WHEN $2 THEN $3 ELSE display_name END,
+            avatar_url = CASE WHEN $4 THEN $5 ELSE avatar_url END,
+            bio = CASE WHEN $6 THEN $7 ELSE bio END
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(user_uuid)
+    .bind(payload.display_name.is_some())
+    .bind(payload.display_name.flatten())
+    .bind(payload.avatar_url.is_some())
+    .bind(payload.avatar_url.flatten())
+    .bind(payload.bio.is_some())
+    .bind(payload.bio.flatten())
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    state.user_cache.invalidate(user_uuid).await;
+
+    Ok(Json(user.into()))
+}
+
+pub async fn get_preferences(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+) -> AppResult<Json<UserPreferences>> {
+    let preferences: sqlx::types::Json<UserPreferences> =
+        sqlx::query_scalar("SELECT preferences FROM users WHERE id = $1")
+            .bind(user_uuid)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    Ok(Json(preferences.0))
+}
+
+/// Replaces the caller's preferences; omitted settings are reset to their defaults.
+pub async fn update_preferences(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Json(payload): Json<UserPreferences>,
+) -> AppResult<Json<UserPreferences>> {
+    if let Some(sort) = payload.default_item_sort.as_deref() {
+        if !is_allowed_sort(sort, ITEM_SORT_FIELDS) {
+            let names: Vec<&str> = ITEM_SORT_FIELDS.iter().map(|(param, _)| *param).collect();
+            return Err(AppError::InvalidFields(vec![FieldError {
+                field: "default_item_sort".to_string(),
+                pointer: "/default_item_sort".to_string(),
+                code: "invalid_sort".to_string(),
+                message: format!(
+                    "Cannot sort by '{}'. Allowed: {} (prefix with '-' for descending)",
+                    sort,
+                    names.join(", ")
+                ),
+            }]));
+        }
+    }
+
+    let preferences: sqlx::types::Json<UserPreferences> =
+        sqlx::query_scalar("UPDATE users SET preferences = $1 WHERE id = $2 RETURNING preferences")
+            .bind(sqlx::types::Json(&payload))
+            .bind(user_uuid)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    state.user_cache.invalidate(user_uuid).await;
+
+    Ok(Json(preferences.0))
+}
+
+/// Starts moving the caller to a new email address. The current address stays in
+/// use until the token sent to the new one comes back through
+/// `POST /auth/email/verify`; a new request replaces any pending one.
+pub async fn request_email_change(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Json(payload): Json<ChangeEmail>,
+) -> AppResult<(StatusCode, Json<EmailChangeResponse>)> {
+    payload.validate()?;
 
     let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
         .bind(user_uuid)
@@ -109,5 +331,438 @@ pub async fn get_me(
         .await?
         .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
 
+    let is_valid = verify_password(&payload.current_password, &user.password_hash)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to verify password: {}", e)))?;
+    if !is_valid {
+        return Err(AppError::Authentication(
+            "Current password is incorrect".to_string(),
+        ));
+    }
+
+    if payload.new_email.eq_ignore_ascii_case(&user.email) {
+        return Err(AppError::Validation(
+            "The new email is the same as the current one".to_string(),
+        ));
+    }
+    ensure_email_available(&state, &payload.new_email).await?;
+
+    let token = generate_token(EMAIL_CHANGE_TOKEN_LEN);
+    let expires_at = Utc::now() + Duration::hours(EMAIL_CHANGE_EXPIRATION_HOURS);
+    sqlx::query(
+        r#"
+        INSERT INTO email_changes (user_id, new_email, token_hash, expires_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET new_email = EXCLUDED.new_email,
+            token_hash = EXCLUDED.token_hash,
+            expires_at = EXCLUDED.expires_at,
+            created_at = NOW()
+        "#,
+    )
+    .bind(user_uuid)
+    .bind(&payload.new_email)
+    .bind(hash_token(&token))
+    .bind(expires_at)
+    .execute(&state.db)
+    .await?;
+
+    send_email_change_token(&state, &payload.new_email, &token);
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(EmailChangeResponse {
+            pending_email: payload.new_email,
+            expires_at,
+        }),
+    ))
+}
+
+/// Completes an email change with the token sent to the new address. Needs no
+/// login: the token alone identifies the user and proves the address is theirs.
+pub async fn verify_email_change(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailChange>,
+) -> AppResult<Json<UserResponse>> {
+    let mut tx = state.db.begin().await?;
+
+    // Consume the token atomically so it can't be used twice
+    let (user_id, new_email): (Uuid, String) = sqlx::query_as(
+        r#"
+        DELETE FROM email_changes
+        WHERE token_hash = $1 AND expires_at > NOW()
+        RETURNING user_id, new_email
+        "#,
+    )
+    .bind(hash_token(&payload.token))
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::BadRequest("Invalid or expired email change token".to_string()))?;
+
+    // Someone may have signed up with the address since the change was requested
+    let user = sqlx::query_as::<_, User>("UPDATE users SET email = $2 WHERE id = $1 RETURNING *")
+        .bind(user_id)
+        .bind(&new_email)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(ref db) if db.is_unique_violation() => {
+                AppError::BadRequest("Email is already in use".to_string())
+            }
+            e => e.into(),
+        })?;
+    tx.commit().await?;
+    state.user_cache.invalidate(user_id).await;
+
     Ok(Json(user.into()))
 }
+
+async fn ensure_email_available(state: &AppState, email: &str) -> AppResult<()> {
+    let taken: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE email = $1)")
+        .bind(email)
+        .fetch_one(&state.db)
+        .await?;
+    if taken {
+        return Err(AppError::BadRequest("Email is already in use".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Hands the verification token to the new address's owner. There is no mail
+/// sender yet, so outside production the token is logged for local testing; in
+/// production this is the place to plug one in.
+fn send_email_change_token(state: &AppState, email: &str, token: &str) {
+    if state.config.is_production() {
+        tracing::warn!(
+            "No email sender configured; the email change token for {} was not delivered",
+            email
+        );
+    } else {
+        tracing::info!("Email change token for {}: {}", email, token);
+    }
+}
+
+pub async fn deactivate(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+) -> AppResult<StatusCode> {
+    let mut tx = state.db.begin().await?;
+    ensure_other_admin(&mut tx, user_uuid).await?;
+    let result = sqlx::query("UPDATE users SET is_active = FALSE WHERE id = $1")
+        .bind(user_uuid)
+        .execute(&mut *tx)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+    tx.commit().await?;
+    state.user_cache.invalidate(user_uuid).await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Revokes every token issued to the caller so far and returns a fresh one.
+/// API keys are separate credentials and stay valid.
+pub async fn invalidate_tokens(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    headers: HeaderMap,
+) -> AppResult<(AuthCookie, Json<AuthResponse>)> {
+    // Token versions are only compared when the account is re-checked, so without
+    // that the old tokens would keep working while the caller believes them revoked
+    if !state.config.verify_user_on_request {
+        return Err(AppError::Conflict(
+            "Tokens cannot be revoked while VERIFY_USER_ON_REQUEST=false; \
+             they stay valid until they expire"
+                .to_string(),
+        ));
+    }
+
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET token_version = token_version + 1 WHERE id = $1 RETURNING *",
+    )
+    .bind(user_uuid)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    state.user_cache.invalidate(user_uuid).await;
+
+    let token = create_token(&user, &state.config, &headers)
+        .map_err(|e| AppError::Internal(format!("Failed to create token: {}", e)))?;
+
+    Ok((
+        AuthCookie::issue(&state.config, &token),
+        Json(AuthResponse {
+            token,
+            user: user.into(),
+        }),
+    ))
+}
+
+/// Describes a JWT for gateways that can't verify it themselves. Invalid, expired,
+/// and revoked tokens (or tokens of deactivated users) are reported as inactive.
+pub async fn introspect_token(
+    State(state): State<AppState>,
+    Json(payload): Json<IntrospectRequest>,
+) -> AppResult<Json<IntrospectionResponse>> {
+    let Ok(mut claims) = verify_token(&payload.token, &state.config) else {
+        return Ok(Json(IntrospectionResponse::default()));
+    };
+
+    // Unlike request auth this always consults the database, regardless of
+    // VERIFY_USER_ON_REQUEST; only a failing database is an error
+    match check_account(&state.db, &mut claims).await {
+        Ok(_) => {}
+        Err(e @ (AppError::Database(_) | AppError::ServiceUnavailable(..))) => return Err(e),
+        Err(_) => return Ok(Json(IntrospectionResponse::default())),
+    }
+
+    Ok(Json(IntrospectionResponse {
+        active: true,
+        sub: Some(claims.sub),
+        email: Some(claims.email),
+        role: Some(claims.role),
+        exp: Some(claims.exp),
+        scopes: Some(claims.scopes),
+    }))
+}
+
+/// Debugging aid, routed only outside production: decodes a JWT and says exactly
+/// why it would be refused. Unlike introspection it needs no credentials, so it
+/// would be an oracle for forged or stolen tokens if it were reachable in production.
+pub async fn verify_token_debug(
+    State(state): State<AppState>,
+    Json(payload): Json<IntrospectRequest>,
+) -> AppResult<Json<TokenVerification>> {
+    let claims = match verify_token(&payload.token, &state.config) {
+        Ok(claims) => claims,
+        Err(e) => {
+            return Ok(Json(TokenVerification {
+                valid: false,
+                claims: None,
+                error: Some(token_error_kind(e.kind())),
+                message: Some(e.to_string()),
+            }))
+        }
+    };
+
+    // The checks request auth makes with VERIFY_USER_ON_REQUEST; they only read
+    let mut checked = claims.clone();
+    let rejection = match check_account(&state.db, &mut checked).await {
+        Ok(_) => None,
+        Err(AppError::Unauthorized(message) | AppError::Forbidden(message)) => Some(message),
+        Err(e) => return Err(e),
+    };
+
+    Ok(Json(TokenVerification {
+        valid: rejection.is_none(),
+        claims: Some(claims),
+        error: rejection.as_ref().map(|_| "account_rejected"),
+        message: rejection,
+    }))
+}
+
+fn token_error_kind(kind: &jsonwebtoken::errors::ErrorKind) -> &'static str {
+    use jsonwebtoken::errors::ErrorKind;
+
+    match kind {
+        ErrorKind::ExpiredSignature => "expired_signature",
+        ErrorKind::ImmatureSignature => "immature_signature",
+        ErrorKind::InvalidSignature => "invalid_signature",
+        ErrorKind::InvalidAlgorithm => "invalid_algorithm",
+        ErrorKind::InvalidAudience => "invalid_audience",
+        ErrorKind::MissingRequiredClaim(_) => "missing_required_claim",
+        ErrorKind::Base64(_) | ErrorKind::Json(_) | ErrorKind::Utf8(_) => "malformed",
+        _ => "invalid_token",
+    }
+}
+
+pub async fn reactivate(
+    State(state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(payload): Json<LoginUser>,
+) -> AppResult<(AuthCookie, Json<AuthResponse>)> {
+    // Validate input
+    payload.validate()?;
+
+    // Reactivation requires the same credentials as login
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE email = $1")
+        .bind(&payload.email)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| AppError::Authentication("Invalid email or password".to_string()))?;
+
+    let is_valid = verify_password(&payload.password, &user.password_hash)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to verify password: {}", e)))?;
+
+    if !is_valid {
+        return Err(AppError::Authentication(
+            "Invalid email or password".to_string(),
+        ));
+    }
+
+    ensure_not_locked(&user)?;
+
+    // An admin may lock the account between the read and this update
+    let reactivated =
+        sqlx::query("UPDATE users SET is_active = TRUE WHERE id = $1 AND locked_at IS NULL")
+            .bind(user.id)
+            .execute(&state.db)
+            .await?;
+    if reactivated.rows_affected() == 0 {
+        return Err(locked());
+    }
+
+    upgrade_password_hash(&state, &user, &payload.password).await;
+    let user = record_login(&state, user.id, addr).await?;
+
+    // Generate JWT token
+    let token = create_token(&user, &state.config, &headers)
+        .map_err(|e| AppError::Internal(format!("Failed to create token: {}", e)))?;
+
+    Ok((
+        AuthCookie::issue(&state.config, &token),
+        Json(AuthResponse {
+            token,
+            user: user.into(),
+        }),
+    ))
+}
+
+/// Refuses locked accounts and accounts their owner deactivated, each with its own message.
+fn ensure_can_log_in(user: &User) -> AppResult<()> {
+    ensure_not_locked(user)?;
+
+    if !user.is_active {
+        return Err(AppError::Forbidden(
+            "Account is deactivated. Use POST /auth/reactivate to restore access".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Accounts an admin deactivated only come back through an admin.
+fn ensure_not_locked(user: &User) -> AppResult<()> {
+    match user.locked_at {
+        Some(_) => Err(locked()),
+        None => Ok(()),
+    }
+}
+
+fn locked() -> AppError {
+    AppError::Forbidden(
+        "Account is locked by an administrator. Contact an administrator to restore access"
+            .to_string(),
+    )
+}
+
+/// Rehashes the password at the current `BCRYPT_COST` if the stored hash is
+/// weaker. Only called after the password was verified; failures are logged
+/// and the old hash is kept.
+async fn upgrade_password_hash(state: &AppState, user: &User, password: &str) {
+    if !needs_rehash(&user.password_hash, state.config.bcrypt_cost) {
+        return;
+    }
+
+    let password_hash = match hash_password(password, state.config.bcrypt_cost).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            tracing::warn!("Failed to rehash password: {}", e);
+            return;
+        }
+    };
+
+    // Skip the update if the password changed since it was verified
+    let result =
+        sqlx::query("UPDATE users SET password_hash = $2 WHERE id = $1 AND password_hash = $3")
+            .bind(user.id)
+            .bind(&password_hash)
+            .bind(&user.password_hash)
+            .execute(&state.db)
+            .await;
+    match result {
+        Ok(result) if result.rows_affected() > 0 => tracing::info!(
+            user_id = %user.id,
+            cost = state.config.bcrypt_cost,
+            "Upgraded password hash"
+        ),
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to store rehashed password: {}", e),
+    }
+}
+
+/// Stamps a successful login on the user row and returns the updated user.
+async fn record_login(state: &AppState, user_id: Uuid, addr: SocketAddr) -> AppResult<User> {
+    let user = sqlx::query_as::<_, User>(
+        "UPDATE users SET last_login_at = NOW(), last_login_ip = $2 WHERE id = $1 RETURNING *",
+    )
+    .bind(user_id)
+    .bind(addr.ip().to_string())
+    .fetch_one(&state.db)
+    .await?;
+    state.user_cache.invalidate(user_id).await;
+
+    Ok(user)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ROLE_USER;
+
+    fn user(is_active: bool, locked: bool) -> User {
+        User {
+            id: Uuid::new_v4(),
+            email: "locked@example.com".to_string(),
+            username: "locked".to_string(),
+            password_hash: String::new(),
+            is_active,
+            locked_at: locked.then(Utc::now),
+            role: ROLE_USER.to_string(),
+            last_login_at: None,
+            last_login_ip: None,
+            token_version: 0,
+            preferences: sqlx::types::Json(Default::default()),
+            display_name: None,
+            avatar_url: None,
+            bio: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn message(result: AppResult<()>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn active_accounts_can_log_in() {
+        assert!(ensure_can_log_in(&user(true, false)).is_ok());
+    }
+
+    #[test]
+    fn self_deactivated_accounts_are_pointed_at_reactivation() {
+        let user = user(false, false);
+
+        assert_eq!(
+            message(ensure_can_log_in(&user)),
+            "Forbidden: Account is deactivated. Use POST /auth/reactivate to restore access"
+        );
+        assert!(ensure_not_locked(&user).is_ok());
+    }
+
+    #[test]
+    fn accounts_deactivated_by_an_admin_can_neither_log_in_nor_reactivate() {
+        let user = user(false, true);
+        let locked = "Forbidden: Account is locked by an administrator. \
+                      Contact an administrator to restore access";
+
+        assert_eq!(message(ensure_can_log_in(&user)), locked);
+        assert_eq!(message(ensure_not_locked(&user)), locked);
+    }
+}
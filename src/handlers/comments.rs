@@ -0,0 +1,126 @@
+use axum::{
+    extract::{OriginalUri, Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::{AppError, AppResult},
+    extract::{AuthUser, Json, Query},
+    handlers::item_access,
+    models::{
+        Comment, CreateComment, ListParams, Page, PageLinks, COMMENT_SORT_FIELDS,
+        DEFAULT_COMMENT_SORT, WORKSPACE_OWNER,
+    },
+    AppState,
+};
+
+/// Comments on an item. Anyone who can see the item, viewers included, may join in.
+pub async fn create_comment(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(item_id): Path<Uuid>,
+    Json(payload): Json<CreateComment>,
+) -> AppResult<(StatusCode, Json<Comment>)> {
+    payload.validate()?;
+    if payload.body.trim().is_empty() {
+        return Err(AppError::Validation(
+            "Comment must not be blank".to_string(),
+        ));
+    }
+
+    item_access(&state, user_uuid, item_id).await?;
+
+    let comment = sqlx::query_as::<_, Comment>(
+        r#"
+        WITH inserted AS (
+            INSERT INTO comments (item_id, user_id, body) VALUES ($1, $2, $3) RETURNING *
+        )
+        SELECT inserted.id, inserted.item_id, inserted.user_id, users.username,
+               inserted.body, inserted.created_at, inserted.updated_at
+        FROM inserted JOIN users ON users.id = inserted.user_id
+        "#,
+    )
+    .bind(item_id)
+    .bind(user_uuid)
+    .bind(&payload.body)
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+/// One page of an item's comments, oldest first unless `?sort=-created_at`.
+pub async fn get_comments(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(item_id): Path<Uuid>,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<ListParams>,
+) -> AppResult<Json<Page<Comment>>> {
+    params.validate()?;
+
+    let order_by = params.order_by(COMMENT_SORT_FIELDS, DEFAULT_COMMENT_SORT)?;
+
+    item_access(&state, user_uuid, item_id).await?;
+
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE item_id = $1")
+        .bind(item_id)
+        .fetch_one(&state.db)
+        .await?;
+
+    // `order_by` only ever contains allowlisted columns; `id` keeps pages stable on ties
+    let comments = sqlx::query_as::<_, Comment>(&format!(
+        "SELECT comments.id, comments.item_id, comments.user_id, users.username, \
+                comments.body, comments.created_at, comments.updated_at \
+         FROM comments JOIN users ON users.id = comments.user_id \
+         WHERE comments.item_id = $1 \
+         ORDER BY comments.{}, comments.id LIMIT $2 OFFSET $3",
+        order_by
+    ))
+    .bind(item_id)
+    .bind(params.per_page)
+    .bind(params.offset())
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(Page {
+        data: comments,
+        page: params.page,
+        per_page: params.per_page,
+        total,
+        links: PageLinks::new(&uri, &params, total),
+    }))
+}
+
+/// Deletes a comment. Allowed for its author, the item's creator and owners of
+/// the item's workspace.
+pub async fn delete_comment(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path((item_id, comment_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    let (item, role) = item_access(&state, user_uuid, item_id).await?;
+
+    let author: Uuid =
+        sqlx::query_scalar("SELECT user_id FROM comments WHERE id = $1 AND item_id = $2")
+            .bind(comment_id)
+            .bind(item_id)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Comment not found".to_string()))?;
+
+    if author != user_uuid && item.user_id != user_uuid && role != WORKSPACE_OWNER {
+        return Err(AppError::Forbidden(
+            "Only the comment's author or the item's owner can delete it".to_string(),
+        ));
+    }
+
+    sqlx::query("DELETE FROM comments WHERE id = $1")
+        .bind(comment_id)
+        .execute(&state.db)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,238 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, AppResult},
+    extract::AuthUser,
+    middleware::rate_limited,
+    models::{
+        ApiKey, ApiKeyResponse, Attachment, AttachmentResponse, Comment, DataExport, ExportedItem,
+        ExportedUser, Item, User, WorkspaceResponse,
+    },
+    utils::timestamp::{self, with_timestamp_format},
+    AppState,
+};
+
+/// Items are loaded this many at a time, together with their comments and
+/// attachments, so an export never holds a whole account in memory.
+const EXPORT_PAGE_SIZE: i64 = 100;
+
+/// Chunks the export may run ahead of a slow client.
+const EXPORT_BUFFERED_CHUNKS: usize = 4;
+
+type ExportSender = mpsc::Sender<Result<Bytes, std::io::Error>>;
+
+/// Everything stored about the caller as one JSON document, for data subject
+/// access requests. The document is streamed while it is read from the
+/// database; each user may start one export per `DATA_EXPORT_WINDOW`.
+pub async fn export_user_data(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+) -> AppResult<Response> {
+    if let Err(retry_after) = state.export_rate_limiter.check(user_uuid).await {
+        return Err(rate_limited(retry_after));
+    }
+
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE id = $1")
+        .bind(user_uuid)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    // The writer outlives this handler, so it carries the request's timestamp format along
+    let (tx, rx) = mpsc::channel(EXPORT_BUFFERED_CHUNKS);
+    let format = timestamp::current_format();
+    tokio::spawn(with_timestamp_format(format, async move {
+        if let Err(e) = write_export(&state, user, &tx).await {
+            tracing::warn!("Data export for user {} stopped: {:#}", user_uuid, e);
+            // Failing the body tells the client the download is incomplete
+            let _ = tx.send(Err(std::io::Error::other("export failed"))).await;
+        }
+    }));
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/json"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"export.json\"",
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+async fn write_export(state: &AppState, user: User, tx: &ExportSender) -> anyhow::Result<()> {
+    let workspaces = sqlx::query_as::<_, WorkspaceResponse>(
+        r#"
+        SELECT workspaces.id, workspaces.name, workspaces.personal_user_id IS NOT NULL AS personal,
+               members.role, workspaces.created_at
+        FROM workspaces
+        JOIN workspace_members members ON members.workspace_id = workspaces.id
+        WHERE members.user_id = $1
+        ORDER BY personal DESC, workspaces.created_at ASC
+        "#,
+    )
+    .bind(user.id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let api_keys = sqlx::query_as::<_, ApiKey>(
+        "SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at",
+    )
+    .bind(user.id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let user_id = user.id;
+    let export = DataExport {
+        exported_at: Utc::now(),
+        user: ExportedUser {
+            preferences: user.preferences.0.clone(),
+            user: user.into(),
+        },
+        workspaces,
+        api_keys: api_keys.into_iter().map(ApiKeyResponse::from).collect(),
+    };
+
+    // Reopen the serialized object to append the streamed arrays
+    let mut chunk = serde_json::to_vec(&export)?;
+    chunk.pop();
+    chunk.extend_from_slice(br#","items":["#);
+    send(tx, chunk).await?;
+
+    // Items the user created, with every comment and attachment on them
+    let mut after: Option<(DateTime<Utc>, Uuid)> = None;
+    let mut first = true;
+    loop {
+        let items = sqlx::query_as::<_, Item>(
+            r#"
+            SELECT * FROM items
+            WHERE user_id = $1 AND ($2::TIMESTAMPTZ IS NULL OR (created_at, id) > ($2, $3))
+            ORDER BY created_at, id
+            LIMIT $4
+            "#,
+        )
+        .bind(user_id)
+        .bind(after.map(|(created_at, _)| created_at))
+        .bind(after.map(|(_, id)| id))
+        .bind(EXPORT_PAGE_SIZE)
+        .fetch_all(&state.db)
+        .await?;
+        let Some(last) = items.last() else {
+            break;
+        };
+        after = Some((last.created_at, last.id));
+        let full_page = items.len() as i64 == EXPORT_PAGE_SIZE;
+
+        let ids: Vec<Uuid> = items.iter().map(|item| item.id).collect();
+        let mut comments: HashMap<Uuid, Vec<Comment>> = HashMap::new();
+        for comment in sqlx::query_as::<_, Comment>(
+            r#"
+            SELECT comments.id, comments.item_id, comments.user_id, users.username,
+                   comments.body, comments.created_at, comments.updated_at
+            FROM comments JOIN users ON users.id = comments.user_id
+            WHERE comments.item_id = ANY($1)
+            ORDER BY comments.created_at, comments.id
+            "#,
+        )
+        .bind(&ids)
+        .fetch_all(&state.db)
+        .await?
+        {
+            comments.entry(comment.item_id).or_default().push(comment);
+        }
+        let mut attachments: HashMap<Uuid, Vec<AttachmentResponse>> = HashMap::new();
+        for attachment in sqlx::query_as::<_, Attachment>(
+            "SELECT * FROM attachments WHERE item_id = ANY($1) ORDER BY created_at",
+        )
+        .bind(&ids)
+        .fetch_all(&state.db)
+        .await?
+        {
+            attachments
+                .entry(attachment.item_id)
+                .or_default()
+                .push(attachment.into());
+        }
+
+        let mut chunk = Vec::new();
+        for item in items {
+            let id = item.id;
+            push_element(
+                &mut chunk,
+                &mut first,
+                &ExportedItem {
+                    item: item.into(),
+                    comments: comments.remove(&id).unwrap_or_default(),
+                    attachments: attachments.remove(&id).unwrap_or_default(),
+                },
+            )?;
+        }
+        send(tx, chunk).await?;
+
+        if !full_page {
+            break;
+        }
+    }
+
+    // Comments the user wrote on other people's items
+    send(tx, br#"],"comments":["#.to_vec()).await?;
+    let mut comments = sqlx::query_as::<_, Comment>(
+        r#"
+        SELECT comments.id, comments.item_id, comments.user_id, users.username,
+               comments.body, comments.created_at, comments.updated_at
+        FROM comments
+        JOIN users ON users.id = comments.user_id
+        JOIN items ON items.id = comments.item_id
+        WHERE comments.user_id = $1 AND items.user_id <> $1
+        ORDER BY comments.created_at, comments.id
+        "#,
+    )
+    .bind(user_id)
+    .fetch(&state.db);
+    let mut first = true;
+    let mut chunk = Vec::new();
+    let mut buffered = 0;
+    while let Some(comment) = comments.next().await {
+        push_element(&mut chunk, &mut first, &comment?)?;
+        buffered += 1;
+        if buffered == EXPORT_PAGE_SIZE {
+            send(tx, std::mem::take(&mut chunk)).await?;
+            buffered = 0;
+        }
+    }
+    chunk.extend_from_slice(b"]}");
+    send(tx, chunk).await?;
+
+    Ok(())
+}
+
+/// Appends `value` to a JSON array being written, with a comma unless it's the first.
+fn push_element<T: Serialize>(
+    chunk: &mut Vec<u8>,
+    first: &mut bool,
+    value: &T,
+) -> serde_json::Result<()> {
+    if !std::mem::take(first) {
+        chunk.push(b',');
+    }
+    serde_json::to_writer(chunk, value)
+}
+
+async fn send(tx: &ExportSender, chunk: Vec<u8>) -> anyhow::Result<()> {
+    tx.send(Ok(Bytes::from(chunk)))
+        .await
+        .map_err(|_| anyhow::anyhow!("the client disconnected"))
+}
@@ -1,5 +1,13 @@
-use axum::{http::StatusCode, Json};
+use axum::{extract::State, http::StatusCode, Extension, Json};
 use serde_json::{json, Value};
+use std::sync::{atomic::Ordering, Arc};
+
+use crate::{
+    models::{DEFAULT_PER_PAGE, MAX_PER_PAGE},
+    routes::RouteInfo,
+    utils::timestamp,
+    AppState,
+};
 
 pub async fn health_check() -> (StatusCode, Json<Value>) {
     (
@@ -10,3 +18,83 @@ pub async fn health_check() -> (StatusCode, Json<Value>) {
         })),
     )
 }
+
+/// Readiness: the database answers a query and, with `DEEP_HEALTH_CHECK`, also
+/// accepts a write. `503` while it doesn't. Also reports the database circuit
+/// breaker and the request queue, both of which this check bypasses.
+pub async fn readiness_check(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let result = if state.config.deep_health_check {
+        check_database_write(&state).await
+    } else {
+        sqlx::query("SELECT 1").execute(&state.db).await.map(|_| ())
+    };
+
+    let (status, health) = match result {
+        Ok(()) => (StatusCode::OK, "healthy"),
+        Err(e) => {
+            tracing::warn!("Readiness check failed: {}", e);
+            (StatusCode::SERVICE_UNAVAILABLE, "unhealthy")
+        }
+    };
+
+    (
+        status,
+        Json(json!({
+            "status": health,
+            "database": if status.is_success() { "ok" } else { "error" },
+            "circuit_breaker": state.db_breaker.state(),
+            "shed_requests": state.shed_requests.load(Ordering::Relaxed),
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+        })),
+    )
+}
+
+/// Upserts the `health_check` row and rolls back, so a read-only database
+/// (demoted primary, full disk) fails the check while nothing is kept.
+async fn check_database_write(state: &AppState) -> Result<(), sqlx::Error> {
+    let mut tx = state.db.begin().await?;
+    sqlx::query(
+        "INSERT INTO health_check (id, checked_at) VALUES (1, NOW()) \
+         ON CONFLICT (id) DO UPDATE SET checked_at = EXCLUDED.checked_at",
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.rollback().await
+}
+
+/// Identifies the running build: crate version, git commit and build time.
+pub async fn version() -> Json<Value> {
+    let built_at = env!("BUILD_TIMESTAMP")
+        .parse()
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .map(|at| at.to_rfc3339());
+
+    Json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "commit": option_env!("GIT_COMMIT"),
+        "built_at": built_at,
+    }))
+}
+
+/// The server's clock, for clients aligning `since` cursors or measuring skew, with
+/// the settings that shape list responses. Public, so nothing secret goes here.
+pub async fn server_time() -> Json<Value> {
+    let now = chrono::Utc::now();
+
+    Json(json!({
+        "server_time": now.to_rfc3339(),
+        "epoch_millis": now.timestamp_millis(),
+        "timezone": "UTC",
+        "timestamp_format": timestamp::current_format().as_str(),
+        "pagination": {
+            "default_per_page": DEFAULT_PER_PAGE,
+            "max_per_page": MAX_PER_PAGE,
+        },
+    }))
+}
+
+/// Lists every registered route. Only mounted outside production.
+pub async fn list_routes(Extension(routes): Extension<Arc<Vec<RouteInfo>>>) -> Json<Value> {
+    Json(json!({ "routes": *routes }))
+}
@@ -1,150 +1,1343 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    Json,
+    extract::{OriginalUri, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
 };
+use chrono::{DateTime, SubsecRound, Utc};
+use sqlx::{FromRow, Row};
+use std::{collections::HashSet, convert::Infallible, sync::Arc};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
     error::{AppError, AppResult},
-    models::{CreateItem, Item, ItemResponse, UpdateItem},
+    events::{publish_item_event, ItemEvent, ItemEventKind},
+    extract::{AuthUser, Json, Negotiated, Query, ResponseFormat},
+    handlers::{
+        attachment_keys, item_access, member_workspace_ids, remove_attachment_blobs,
+        require_item_write, target_workspace,
+    },
+    models::{
+        allowed_status_transitions, is_allowed_sort, is_valid_external_id, normalize_status,
+        normalize_tag, normalize_tags, parse_item_fields, statuses_allowed_into, BatchCreateItems,
+        BatchDeleteItems, BatchGetItems, BatchItemResult, BatchMode, BatchQuery,
+        BulkStatusResponse, BulkStatusUpdate, CreateItem, FieldMaskQuery, Item, ItemChanges,
+        ItemListFilter, ItemReadQuery, ItemRender, ItemResponse, ItemTombstone, ListParams, Page,
+        PageLinks, PatchItemResponse, ProjectedItem, ReorderItems, UpdateItem, UpsertItem,
+        UserPreferences, DEFAULT_ITEM_SORT, ITEM_SORT_FIELDS, ITEM_TOMBSTONE_RETENTION_DAYS,
+        MAX_BATCH_SIZE, MAX_EXTERNAL_ID_LEN, UPDATABLE_ITEM_FIELDS,
+    },
+    single_flight::ItemListKey,
+    utils::{
+        http::{format_http_date, parse_http_date},
+        markdown::render_markdown,
+        timestamp::{self, with_timestamp_format},
+    },
     AppState,
 };
 
 pub async fn create_item(
     State(state): State<AppState>,
-    user_id: axum::Extension<String>,
+    AuthUser(user_uuid): AuthUser,
     Json(payload): Json<CreateItem>,
 ) -> AppResult<(StatusCode, Json<ItemResponse>)> {
-    // Validate input
-    payload
-        .validate()
-        .map_err(|e| AppError::Validation(e.to_string()))?;
+    let item = insert_item(&state, user_uuid, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(item.into())))
+}
+
+/// Creates several items. By default all are created or none are (`201` with the
+/// items); with `?mode=partial` each is attempted independently (`207` with
+/// per-element results).
+pub async fn batch_create_items(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Query(query): Query<BatchQuery>,
+    Json(payload): Json<BatchCreateItems>,
+) -> AppResult<Response> {
+    if query.mode == BatchMode::Partial {
+        // Only the batch size is checked up front; each item is validated on its own
+        if payload.items.is_empty() || payload.items.len() as u64 > MAX_BATCH_SIZE {
+            return Err(AppError::Validation(format!(
+                "Between 1 and {} items per batch",
+                MAX_BATCH_SIZE
+            )));
+        }
+
+        let mut results = Vec::with_capacity(payload.items.len());
+        for (index, item) in payload.items.into_iter().enumerate() {
+            let result = match insert_item(&state, user_uuid, item).await {
+                Ok(item) => BatchItemResult {
+                    index,
+                    status: StatusCode::CREATED.as_u16(),
+                    id: Some(item.id),
+                    item: Some(ItemResponse::from(item)),
+                    error: None,
+                },
+                Err(e) => BatchItemResult::failed(index, None, e),
+            };
+            results.push(result);
+        }
+
+        return Ok((StatusCode::MULTI_STATUS, Json(results)).into_response());
+    }
+
+    payload.validate()?;
+
+    let mut prepared = Vec::with_capacity(payload.items.len());
+    for item in &payload.items {
+        check_due_at(&state, item.due_at)?;
+        let tags = prepare_tags(&state, item.tags.clone())?;
+        let workspace_id = target_workspace(&state, user_uuid, item.workspace_id).await?;
+        prepared.push((workspace_id, tags));
+    }
+
+    let mut tx = state.db.begin().await?;
+    let mut items = Vec::with_capacity(payload.items.len());
+    for (item, (workspace_id, tags)) in payload.items.iter().zip(prepared) {
+        let item = sqlx::query_as::<_, Item>(
+            "INSERT INTO items (user_id, workspace_id, title, description, status, tags, due_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *",
+        )
+        .bind(user_uuid)
+        .bind(workspace_id)
+        .bind(&item.title)
+        .bind(&item.description)
+        .bind(&state.config.default_item_status)
+        .bind(tags)
+        .bind(item.due_at)
+        .fetch_one(&mut *tx)
+        .await?;
+        items.push(item);
+    }
+    tx.commit().await?;
+
+    for item in &items {
+        publish_item_event(
+            &state,
+            ItemEvent::new(ItemEventKind::Created, item, user_uuid),
+        )
+        .await;
+    }
 
-    let user_uuid: Uuid = user_id
-        .0
-        .parse()
-        .map_err(|_| AppError::Internal("Invalid user ID format".to_string()))?;
+    let responses: Vec<ItemResponse> = items.into_iter().map(Into::into).collect();
+    Ok((StatusCode::CREATED, Json(responses)).into_response())
+}
 
+/// Validates and inserts a single item, announcing it to subscribers.
+async fn insert_item(state: &AppState, user_uuid: Uuid, payload: CreateItem) -> AppResult<Item> {
+    payload.validate()?;
+    check_due_at(state, payload.due_at)?;
+    let tags = prepare_tags(state, payload.tags)?;
+
+    let workspace_id = target_workspace(state, user_uuid, payload.workspace_id).await?;
     let item = sqlx::query_as::<_, Item>(
-        "INSERT INTO items (user_id, title, description) VALUES ($1, $2, $3) RETURNING *",
+        "INSERT INTO items (user_id, workspace_id, title, description, status, tags, due_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING *",
     )
     .bind(user_uuid)
+    .bind(workspace_id)
     .bind(&payload.title)
     .bind(&payload.description)
+    .bind(&state.config.default_item_status)
+    .bind(tags)
+    .bind(payload.due_at)
     .fetch_one(&state.db)
     .await?;
 
-    Ok((StatusCode::CREATED, Json(item.into())))
+    publish_item_event(
+        state,
+        ItemEvent::new(ItemEventKind::Created, &item, user_uuid),
+    )
+    .await;
+
+    Ok(item)
 }
 
 pub async fn get_items(
     State(state): State<AppState>,
-    user_id: axum::Extension<String>,
-) -> AppResult<Json<Vec<ItemResponse>>> {
-    let user_uuid: Uuid = user_id
-        .0
-        .parse()
-        .map_err(|_| AppError::Internal("Invalid user ID format".to_string()))?;
+    AuthUser(user_uuid): AuthUser,
+    Query(params): Query<ListParams>,
+    Query(filter): Query<ItemListFilter>,
+    format: ResponseFormat,
+    headers: HeaderMap,
+    OriginalUri(uri): OriginalUri,
+) -> AppResult<Response> {
+    params.validate()?;
+    filter.validate()?;
+
+    let fields: Option<Arc<[&str]>> = parse_item_fields(filter.fields.as_deref())?.map(Into::into);
+
+    if let Some(since) = filter.since {
+        if filter.q.is_some()
+            || filter.tag.is_some()
+            || params.status.is_some()
+            || filter.overdue.is_some()
+            || filter.due_before.is_some()
+            || filter.due_after.is_some()
+            || filter.completed_after.is_some()
+            || filter.archived
+        {
+            return Err(AppError::BadRequest(
+                "since can't be combined with q, tag, status, date or archived filters".to_string(),
+            ));
+        }
+        return item_changes(
+            &state,
+            user_uuid,
+            since,
+            filter.workspace_id,
+            fields,
+            format,
+        )
+        .await;
+    }
+
+    // Without an explicit `?sort=`, searches are ordered by relevance and other lists
+    // fall back to the user's preference. A stored value the allowlist no longer
+    // accepts is ignored rather than failing the list
+    let preferred_sort = if params.sort.is_none() && filter.q.is_none() {
+        let preferences: Option<sqlx::types::Json<UserPreferences>> =
+            sqlx::query_scalar("SELECT preferences FROM users WHERE id = $1")
+                .bind(user_uuid)
+                .fetch_optional(&state.db)
+                .await?;
+        preferences
+            .and_then(|p| p.0.default_item_sort)
+            .filter(|sort| is_allowed_sort(sort, ITEM_SORT_FIELDS))
+    } else {
+        None
+    };
+    let order_by = if params.sort.is_none() && filter.q.is_some() {
+        "rank DESC".to_string()
+    } else {
+        params.order_by(
+            ITEM_SORT_FIELDS,
+            preferred_sort.as_deref().unwrap_or(DEFAULT_ITEM_SORT),
+        )?
+    };
+
+    // Read the version before the list so a concurrent write can only make it look older
+    let last_modified: Option<DateTime<Utc>> =
+        sqlx::query_scalar("SELECT modified_at FROM item_list_versions WHERE user_id = $1")
+            .bind(user_uuid)
+            .fetch_optional(&state.db)
+            .await?;
+    // The ETag carries the full version; HTTP dates only have second precision
+    let etag = last_modified.map(|at| format!("W/\"{}\"", at.timestamp_micros()));
+    let last_modified = last_modified.map(|at| at.trunc_subsecs(0));
+
+    // If-None-Match takes precedence, and If-Modified-Since is ignored when it is present.
+    // A version in the same second as the client's date may be newer, so that only matches
+    // when it is strictly earlier.
+    let not_modified = match headers.get(header::IF_NONE_MATCH) {
+        Some(value) => match (&etag, value.to_str()) {
+            (Some(etag), Ok(candidates)) => candidates
+                .split(',')
+                .map(str::trim)
+                .any(|candidate| candidate == "*" || weak_etag_eq(candidate, etag)),
+            _ => false,
+        },
+        None => {
+            let if_modified_since = headers
+                .get(header::IF_MODIFIED_SINCE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(parse_http_date);
+            matches!(
+                (last_modified, if_modified_since),
+                (Some(modified), Some(since)) if modified < since
+            )
+        }
+    };
+    if not_modified {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        insert_version_headers(&mut response, last_modified, etag.as_deref());
+        return Ok(response);
+    }
+
+    // Identical concurrent list requests share one pair of queries when enabled
+    let key = ItemListKey {
+        user_id: user_uuid,
+        workspace_id: filter.workspace_id,
+        status: params.status.as_deref().map(normalize_status),
+        tag: filter.tag.as_deref().map(normalize_tag),
+        search: filter.q,
+        due_before: filter.due_before,
+        due_after: filter.due_after,
+        completed_after: filter.completed_after,
+        overdue: filter.overdue,
+        archived: filter.archived,
+        order_by,
+        per_page: params.per_page,
+        offset: params.offset(),
+    };
+    let (total, items) = state
+        .item_list_flight
+        .run(key.clone(), || fetch_item_page(&state, key))
+        .await?;
+
+    let page = Page {
+        data: items
+            .iter()
+            .cloned()
+            .map(|(item, rank)| ProjectedItem {
+                item: ItemResponse {
+                    rank,
+                    ..item.into()
+                },
+                fields: fields.clone(),
+            })
+            .collect(),
+        page: params.page,
+        per_page: params.per_page,
+        total,
+        links: PageLinks::new(&uri, &params, total),
+    };
+
+    // Users who never had items have no version yet, so no Last-Modified or ETag is sent
+    let mut response = Negotiated(format, page).into_response();
+    response
+        .headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+    insert_version_headers(&mut response, last_modified, etag.as_deref());
+
+    Ok(response)
+}
+
+/// Weak comparison (RFC 9110 section 8.8.3.2): only the opaque tags have to match.
+fn weak_etag_eq(a: &str, b: &str) -> bool {
+    a.trim_start_matches("W/") == b.trim_start_matches("W/")
+}
+
+fn insert_version_headers(
+    response: &mut Response,
+    last_modified: Option<DateTime<Utc>>,
+    etag: Option<&str>,
+) {
+    if let Some(Ok(value)) = last_modified.map(|at| format_http_date(at).parse()) {
+        response.headers_mut().insert(header::LAST_MODIFIED, value);
+    }
+    if let Some(Ok(value)) = etag.map(HeaderValue::from_str) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+}
+
+/// The `?due_before=`, `?due_after=` and `?overdue=` conditions, bound as `$6` to `$8`.
+/// Items without a due date are never overdue.
+const DUE_FILTER: &str = "($6::TIMESTAMPTZ IS NULL OR due_at < $6) \
+     AND ($7::TIMESTAMPTZ IS NULL OR due_at > $7) \
+     AND ($8::BOOL IS NULL OR (COALESCE(due_at < NOW(), FALSE) AND status <> 'done') = $8)";
+
+/// Counts the matching items across the user's workspaces and loads one page of them.
+async fn fetch_item_page(
+    state: &AppState,
+    key: ItemListKey,
+) -> AppResult<(i64, Arc<Vec<(Item, Option<f32>)>>)> {
+    // Searches made only of stop words or punctuation give an empty tsquery that
+    // matches nothing, so those fall back to a substring match
+    let mut search = key.search.clone();
+    let full_text = match &search {
+        Some(q) => {
+            let full_text: bool =
+                sqlx::query_scalar("SELECT numnode(websearch_to_tsquery('english', $1)) > 0")
+                    .bind(q)
+                    .fetch_one(&state.db)
+                    .await?;
+            if !full_text {
+                search = Some(format!("%{}%", escape_like(q)));
+            }
+            full_text
+        }
+        None => false,
+    };
+    let (search_filter, rank) = match (&search, full_text) {
+        (None, _) => ("$4::TEXT IS NULL", "NULL::REAL"),
+        (Some(_), true) => (
+            "search_vector @@ websearch_to_tsquery('english', $4)",
+            "ts_rank(search_vector, websearch_to_tsquery('english', $4))",
+        ),
+        (Some(_), false) => ("(title ILIKE $4 OR description ILIKE $4)", "0::REAL"),
+    };
+
+    let total: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM items \
+         WHERE workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = $1) \
+           AND ($2::TEXT IS NULL OR status = $2) AND ($3::UUID IS NULL OR workspace_id = $3) \
+           AND ($5::TEXT IS NULL OR tags @> ARRAY[$5]) AND {} AND {} \
+           AND (archived_at IS NOT NULL) = $9 \
+           AND ($10::TIMESTAMPTZ IS NULL OR completed_at > $10)",
+        search_filter, DUE_FILTER
+    ))
+    .bind(key.user_id)
+    .bind(&key.status)
+    .bind(key.workspace_id)
+    .bind(&search)
+    .bind(&key.tag)
+    .bind(key.due_before)
+    .bind(key.due_after)
+    .bind(key.overdue)
+    .bind(key.archived)
+    .bind(key.completed_after)
+    .fetch_one(&state.db)
+    .await?;
+
+    // `order_by` only ever contains allowlisted columns; `id` keeps pages stable on ties
+    let rows = sqlx::query(&format!(
+        "SELECT *, {} AS rank FROM items \
+         WHERE workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = $1) \
+           AND ($2::TEXT IS NULL OR status = $2) AND ($3::UUID IS NULL OR workspace_id = $3) \
+           AND ($5::TEXT IS NULL OR tags @> ARRAY[$5]) AND {} AND {} \
+           AND (archived_at IS NOT NULL) = $9 \
+           AND ($10::TIMESTAMPTZ IS NULL OR completed_at > $10) \
+         ORDER BY {}, id LIMIT $11 OFFSET $12",
+        rank, search_filter, DUE_FILTER, key.order_by
+    ))
+    .bind(key.user_id)
+    .bind(&key.status)
+    .bind(key.workspace_id)
+    .bind(&search)
+    .bind(&key.tag)
+    .bind(key.due_before)
+    .bind(key.due_after)
+    .bind(key.overdue)
+    .bind(key.archived)
+    .bind(key.completed_after)
+    .bind(key.per_page)
+    .bind(key.offset)
+    .fetch_all(&state.db)
+    .await?;
+
+    let items = rows
+        .iter()
+        .map(|row| Ok((Item::from_row(row)?, row.try_get("rank")?)))
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+    Ok((total, Arc::new(items)))
+}
+
+/// Delta sync: every item changed after `since` and every item deleted since,
+/// oldest change first and unpaginated, read from one snapshot so `server_time`
+/// is a consistent cursor for the next call.
+///
+/// Items of workspaces joined since are all sent, and those of workspaces left
+/// since come back as deletions, so membership changes sync like item changes.
+async fn item_changes(
+    state: &AppState,
+    user_uuid: Uuid,
+    since: DateTime<Utc>,
+    workspace_id: Option<Uuid>,
+    fields: Option<Arc<[&'static str]>>,
+    format: ResponseFormat,
+) -> AppResult<Response> {
+    if since < Utc::now() - chrono::Duration::days(ITEM_TOMBSTONE_RETENTION_DAYS) {
+        return Err(AppError::Validation(format!(
+            "since must be within the last {} days; fetch the full list instead",
+            ITEM_TOMBSTONE_RETENTION_DAYS
+        )));
+    }
+
+    let mut tx = state.db.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+    // `updated_at` is when the writing transaction began, so one still running
+    // commits changes dated before now that this snapshot can't see. The cursor
+    // goes back to the oldest open transaction's start so the next call picks them
+    // up; clients may get an item twice and keep the copy with the later `updated_at`
+    let server_time: DateTime<Utc> = sqlx::query_scalar(
+        r#"
+        SELECT LEAST(CURRENT_TIMESTAMP, MIN(xact_start)) FROM pg_stat_activity
+        WHERE datname = current_database() AND pid <> pg_backend_pid()
+        "#,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
 
     let items = sqlx::query_as::<_, Item>(
-        "SELECT * FROM items WHERE user_id = $1 ORDER BY created_at DESC",
+        r#"
+        SELECT items.* FROM items
+        JOIN workspace_members members
+          ON members.workspace_id = items.workspace_id AND members.user_id = $1
+        WHERE ($2::UUID IS NULL OR items.workspace_id = $2)
+          AND (items.updated_at > $3 OR members.created_at > $3)
+        ORDER BY items.updated_at, items.id
+        "#,
     )
     .bind(user_uuid)
-    .fetch_all(&state.db)
+    .bind(workspace_id)
+    .bind(since)
+    .fetch_all(&mut *tx)
     .await?;
 
-    let responses: Vec<ItemResponse> = items.into_iter().map(Into::into).collect();
-    Ok(Json(responses))
+    let deleted = sqlx::query_as::<_, ItemTombstone>(
+        r#"
+        WITH departed AS (
+            SELECT workspace_id, left_at FROM workspace_departures
+            WHERE user_id = $1 AND left_at > $3
+              AND workspace_id NOT IN (SELECT workspace_id FROM workspace_members WHERE user_id = $1)
+        )
+        SELECT id, workspace_id, deleted_at FROM item_tombstones
+        WHERE (workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = $1)
+               OR workspace_id IN (SELECT workspace_id FROM departed))
+          AND ($2::UUID IS NULL OR workspace_id = $2) AND deleted_at > $3
+        UNION ALL
+        SELECT items.id, items.workspace_id, departed.left_at FROM items
+        JOIN departed ON departed.workspace_id = items.workspace_id
+        WHERE $2::UUID IS NULL OR items.workspace_id = $2
+        ORDER BY deleted_at, id
+        "#,
+    )
+    .bind(user_uuid)
+    .bind(workspace_id)
+    .bind(since)
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let changes = ItemChanges {
+        items: items
+            .into_iter()
+            .map(|item| ProjectedItem {
+                item: item.into(),
+                fields: fields.clone(),
+            })
+            .collect(),
+        deleted,
+        server_time,
+    };
+
+    let mut response = Negotiated(format, changes).into_response();
+    response
+        .headers_mut()
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+
+    Ok(response)
+}
+
+/// Escapes `ILIKE` wildcards so the search text matches literally.
+pub(crate) fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
 
 pub async fn get_item(
     State(state): State<AppState>,
-    user_id: axum::Extension<String>,
+    AuthUser(user_uuid): AuthUser,
     Path(item_id): Path<Uuid>,
-) -> AppResult<Json<ItemResponse>> {
-    let user_uuid: Uuid = user_id
-        .0
-        .parse()
-        .map_err(|_| AppError::Internal("Invalid user ID format".to_string()))?;
+    Query(query): Query<ItemReadQuery>,
+    format: ResponseFormat,
+) -> AppResult<impl IntoResponse> {
+    let fields = parse_item_fields(query.fields.as_deref())?.map(Into::into);
+    let (item, _) = item_access(&state, user_uuid, item_id).await?;
 
-    let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = $1 AND user_id = $2")
-        .bind(item_id)
-        .bind(user_uuid)
-        .fetch_optional(&state.db)
-        .await?
-        .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
+    // Descriptions are stored as written; rendering happens on every read
+    let description_html = match query.render {
+        Some(ItemRender::Html) => item.description.as_deref().map(render_markdown),
+        None => None,
+    };
 
-    Ok(Json(item.into()))
+    Ok((
+        [(header::VARY, "accept")],
+        Negotiated(
+            format,
+            ProjectedItem {
+                item: ItemResponse {
+                    description_html,
+                    ..item.into()
+                },
+                fields,
+            },
+        ),
+    ))
+}
+
+/// The requested items the caller can see, in the order their ids were given.
+/// Ids that don't exist or belong to other users' workspaces are left out.
+pub async fn batch_get_items(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    format: ResponseFormat,
+    Json(payload): Json<BatchGetItems>,
+) -> AppResult<impl IntoResponse> {
+    payload.validate()?;
+
+    let mut items = sqlx::query_as::<_, Item>(
+        r#"
+        SELECT * FROM items
+        WHERE id = ANY($1)
+          AND workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = $2)
+        "#,
+    )
+    .bind(&payload.ids)
+    .bind(user_uuid)
+    .fetch_all(&state.db)
+    .await?;
+
+    // Repeated ids come back once, at their first position
+    items.sort_by_key(|item| payload.ids.iter().position(|id| *id == item.id));
+    let items: Vec<ItemResponse> = items.into_iter().map(ItemResponse::from).collect();
+
+    Ok(([(header::VARY, "accept")], Negotiated(format, items)))
 }
 
 pub async fn update_item(
     State(state): State<AppState>,
-    user_id: axum::Extension<String>,
+    AuthUser(user_uuid): AuthUser,
     Path(item_id): Path<Uuid>,
     Json(payload): Json<UpdateItem>,
 ) -> AppResult<Json<ItemResponse>> {
     // Validate input
-    payload
-        .validate()
-        .map_err(|e| AppError::Validation(e.to_string()))?;
-
-    let user_uuid: Uuid = user_id
-        .0
-        .parse()
-        .map_err(|_| AppError::Internal("Invalid user ID format".to_string()))?;
-
-    // Check if item exists and belongs to user
-    let _existing_item =
-        sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = $1 AND user_id = $2")
-            .bind(item_id)
+    payload.validate()?;
+
+    let (_, item) = apply_item_update(&state, user_uuid, item_id, payload).await?;
+
+    Ok(Json(item.into()))
+}
+
+pub async fn patch_item(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(item_id): Path<Uuid>,
+    Query(query): Query<FieldMaskQuery>,
+    Json(mut payload): Json<UpdateItem>,
+) -> AppResult<Json<PatchItemResponse>> {
+    // Only fields named in the mask are written; without a mask every provided field is written
+    if let Some(fields) = query.fields.as_deref() {
+        let mask: Vec<&str> = fields
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .collect();
+        if let Some(unknown) = mask.iter().find(|f| !UPDATABLE_ITEM_FIELDS.contains(f)) {
+            return Err(AppError::Validation(format!(
+                "Unknown field '{}' in field mask. Allowed fields: {}",
+                unknown,
+                UPDATABLE_ITEM_FIELDS.join(", ")
+            )));
+        }
+
+        if !mask.contains(&"title") {
+            payload.title = None;
+        }
+        if !mask.contains(&"description") {
+            payload.description = None;
+        }
+        if !mask.contains(&"status") {
+            payload.status = None;
+        }
+        if !mask.contains(&"tags") {
+            payload.tags = None;
+        }
+        if !mask.contains(&"due_at") {
+            payload.due_at = None;
+        }
+    }
+
+    // Validate after masking so fields the caller asked to skip cannot fail the request
+    payload.validate()?;
+
+    let (before, after) = apply_item_update(&state, user_uuid, item_id, payload).await?;
+
+    let mut changed_fields = Vec::new();
+    if before.title != after.title {
+        changed_fields.push("title");
+    }
+    if before.description != after.description {
+        changed_fields.push("description");
+    }
+    if before.status != after.status {
+        changed_fields.push("status");
+    }
+    if before.tags != after.tags {
+        changed_fields.push("tags");
+    }
+    if before.due_at != after.due_at {
+        changed_fields.push("due_at");
+    }
+
+    Ok(Json(PatchItemResponse {
+        item: after.into(),
+        changed_fields,
+    }))
+}
+
+/// Creates or replaces the caller's item with the given external id: `201` when it
+/// was created, `200` when an existing item was updated.
+pub async fn upsert_item_by_external_id(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(external_id): Path<String>,
+    Json(payload): Json<UpsertItem>,
+) -> AppResult<(StatusCode, Json<ItemResponse>)> {
+    // Validate input
+    payload.validate()?;
+    if !is_valid_external_id(&external_id) {
+        return Err(AppError::Validation(format!(
+            "External id must be 1-{} characters of letters, digits, '-', '_', '.' or ':'",
+            MAX_EXTERNAL_ID_LEN
+        )));
+    }
+    check_due_at(&state, payload.due_at)?;
+    let tags = payload
+        .tags
+        .map(|tags| prepare_tags(&state, tags))
+        .transpose()?;
+    let status = payload.status.as_deref().map(prepare_status).transpose()?;
+
+    if state.config.enforce_status_transitions && status.is_some() {
+        let current: Option<String> =
+            sqlx::query_scalar("SELECT status FROM items WHERE user_id = $1 AND external_id = $2")
+                .bind(user_uuid)
+                .bind(&external_id)
+                .fetch_optional(&state.db)
+                .await?;
+        if let Some(current) = current {
+            check_status_transition(&state, &current, status.as_deref())?;
+        }
+    }
+
+    // New items go to the personal workspace; an existing one is only updated while
+    // the user may still change items in its workspace. `xmax` is only zero for a
+    // row this statement inserted
+    let workspace_id = target_workspace(&state, user_uuid, None).await?;
+    let row = sqlx::query(
+        r#"
+        INSERT INTO items
+            (user_id, workspace_id, external_id, title, description, status, tags, due_at)
+        VALUES ($1, $2, $3, $4, $5, COALESCE($6, $7), COALESCE($8, '{}'), $9)
+        ON CONFLICT (user_id, external_id) DO UPDATE
+        SET title = EXCLUDED.title,
+            description = EXCLUDED.description,
+            status = COALESCE($6, items.status),
+            tags = COALESCE($8, items.tags),
+            due_at = COALESCE($9, items.due_at)
+        WHERE items.workspace_id IN (
+            SELECT workspace_id FROM workspace_members
+            WHERE user_id = $1 AND role IN ('owner', 'editor')
+        ) AND ($10::TEXT[] IS NULL OR items.status = ANY($10))
+        RETURNING *, (xmax = 0) AS inserted
+        "#,
+    )
+    .bind(user_uuid)
+    .bind(workspace_id)
+    .bind(&external_id)
+    .bind(&payload.title)
+    .bind(&payload.description)
+    .bind(&status)
+    .bind(&state.config.default_item_status)
+    .bind(tags)
+    .bind(payload.due_at)
+    .bind(statuses_allowed_from(&state, status.as_deref()))
+    .fetch_optional(&state.db)
+    .await?;
+    let Some(row) = row else {
+        // Either the status changed after it was checked or the user lost write access
+        if state.config.enforce_status_transitions && status.is_some() {
+            let current: Option<String> = sqlx::query_scalar(
+                "SELECT status FROM items WHERE user_id = $1 AND external_id = $2",
+            )
             .bind(user_uuid)
+            .bind(&external_id)
             .fetch_optional(&state.db)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
+            .await?;
+            if let Some(current) = current {
+                check_status_transition(&state, &current, status.as_deref())?;
+            }
+        }
+        return Err(AppError::Forbidden(
+            "You can no longer change items in this item's workspace".to_string(),
+        ));
+    };
+
+    let item = Item::from_row(&row)?;
+    let (status, kind) = if row.try_get::<bool, _>("inserted")? {
+        (StatusCode::CREATED, ItemEventKind::Created)
+    } else {
+        (StatusCode::OK, ItemEventKind::Updated)
+    };
+
+    publish_item_event(&state, ItemEvent::new(kind, &item, user_uuid)).await;
+
+    Ok((status, Json(item.into())))
+}
+
+/// Applies an update to an item the user may change, returning the item before and after.
+async fn apply_item_update(
+    state: &AppState,
+    user_uuid: Uuid,
+    item_id: Uuid,
+    payload: UpdateItem,
+) -> AppResult<(Item, Item)> {
+    // Check if item exists and the user's workspace role allows changing it
+    let (existing_item, role) = item_access(state, user_uuid, item_id).await?;
+    require_item_write(&role)?;
+
+    let status = payload.status.as_deref().map(prepare_status).transpose()?;
+    check_status_transition(state, &existing_item.status, status.as_deref())?;
+    let tags = payload
+        .tags
+        .map(|tags| prepare_tags(state, tags))
+        .transpose()?;
 
     // Update item
-    let item = sqlx::query_as::<_, Item>(
+    let updated_item = sqlx::query_as::<_, Item>(
         r#"
         UPDATE items 
         SET title = COALESCE($1, title),
             description = COALESCE($2, description),
-            status = COALESCE($3, status)
-        WHERE id = $4 AND user_id = $5
+            status = COALESCE($3, status),
+            tags = COALESCE($5, tags),
+            due_at = CASE WHEN $6 THEN $7 ELSE due_at END
+        WHERE id = $4 AND ($8::TEXT[] IS NULL OR status = ANY($8))
         RETURNING *
         "#,
     )
     .bind(payload.title)
     .bind(payload.description)
-    .bind(payload.status)
+    .bind(&status)
     .bind(item_id)
-    .bind(user_uuid)
-    .fetch_one(&state.db)
+    .bind(tags)
+    .bind(payload.due_at.is_some())
+    .bind(payload.due_at.flatten())
+    .bind(statuses_allowed_from(state, status.as_deref()))
+    .fetch_optional(&state.db)
     .await?;
+    let Some(updated_item) = updated_item else {
+        // The status changed after it was checked; judge the transition from the new one
+        let (current, _) = item_access(state, user_uuid, item_id).await?;
+        check_status_transition(state, &current.status, status.as_deref())?;
+        return Err(AppError::Conflict(
+            "Item was changed concurrently, retry the update".to_string(),
+        ));
+    };
+
+    publish_item_event(
+        state,
+        ItemEvent::new(ItemEventKind::Updated, &updated_item, user_uuid),
+    )
+    .await;
+
+    Ok((existing_item, updated_item))
+}
+
+/// Normalizes tags for storage, rejecting more distinct tags than `MAX_TAGS_PER_ITEM`.
+fn prepare_tags(state: &AppState, tags: Vec<String>) -> AppResult<Vec<String>> {
+    let tags = normalize_tags(tags);
+    if tags.len() > state.config.max_tags_per_item {
+        return Err(AppError::Validation(format!(
+            "At most {} tags per item",
+            state.config.max_tags_per_item
+        )));
+    }
+
+    Ok(tags)
+}
+
+/// Normalizes a status for storage; one that is only whitespace is refused.
+fn prepare_status(status: &str) -> AppResult<String> {
+    let status = normalize_status(status);
+    if status.is_empty() {
+        return Err(AppError::Validation("Status must not be blank".to_string()));
+    }
+
+    Ok(status)
+}
+
+/// Rejects due dates further in the past than `DUE_AT_MAX_PAST` on new items, which
+/// are almost always typos (a wrong year) rather than real deadlines.
+fn check_due_at(state: &AppState, due_at: Option<DateTime<Utc>>) -> AppResult<()> {
+    let earliest = Utc::now() - chrono::Duration::seconds(state.config.due_at_max_past_secs);
+    if due_at.is_some_and(|due_at| due_at < earliest) {
+        return Err(AppError::Validation(format!(
+            "due_at must not be before {}",
+            earliest.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        )));
+    }
+
+    Ok(())
+}
+
+/// Rejects moving an item from `from` to `to` when transition rules are enforced.
+fn check_status_transition(state: &AppState, from: &str, to: Option<&str>) -> AppResult<()> {
+    if !state.config.enforce_status_transitions {
+        return Ok(());
+    }
+
+    if let Some(status) = to {
+        let allowed = allowed_status_transitions(from);
+        if status != from && !allowed.contains(&status) {
+            return Err(AppError::UnprocessableEntity(format!(
+                "Cannot change status from '{}' to '{}'. Allowed next statuses: {}",
+                from,
+                status,
+                if allowed.is_empty() {
+                    "none".to_string()
+                } else {
+                    allowed.join(", ")
+                }
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Statuses an item may still have for a checked change to `to` to go through, bound
+/// in the UPDATE's WHERE clause since the status may change between the check and
+/// the write. `None` when any status may.
+fn statuses_allowed_from(state: &AppState, to: Option<&str>) -> Option<Vec<String>> {
+    let to = to.filter(|_| state.config.enforce_status_transitions)?;
+    let mut allowed: Vec<String> = statuses_allowed_into(to)
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    allowed.push(to.to_string());
+
+    Some(allowed)
+}
+
+/// Copies an item into the same workspace as a new item of the caller's, with
+/// " (copy)" appended to its title and the default status. Attachments, comments,
+/// the due date and the external id stay with the original.
+pub async fn duplicate_item(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(item_id): Path<Uuid>,
+) -> AppResult<(StatusCode, Json<ItemResponse>)> {
+    let (original, role) = item_access(&state, user_uuid, item_id).await?;
+    require_item_write(&role)?;
+
+    let payload = CreateItem {
+        title: copy_title(&original.title),
+        description: original.description,
+        tags: original.tags,
+        workspace_id: Some(original.workspace_id),
+        due_at: None,
+    };
+    let item = insert_item(&state, user_uuid, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(item.into())))
+}
+
+/// Appends " (copy)", shortening long titles so the result still fits in 255 characters.
+fn copy_title(title: &str) -> String {
+    const SUFFIX: &str = " (copy)";
+    let kept: String = title.chars().take(255 - SUFFIX.len()).collect();
+    format!("{}{}", kept, SUFFIX)
+}
+
+/// Archives an item: it drops out of the default `GET /items` list but is otherwise
+/// untouched. Archiving an archived item keeps its original `archived_at`.
+pub async fn archive_item(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(item_id): Path<Uuid>,
+) -> AppResult<Json<ItemResponse>> {
+    let item = set_archived(&state, user_uuid, item_id, true).await?;
 
     Ok(Json(item.into()))
 }
 
+/// Brings an archived item back into the default list.
+pub async fn unarchive_item(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(item_id): Path<Uuid>,
+) -> AppResult<Json<ItemResponse>> {
+    let item = set_archived(&state, user_uuid, item_id, false).await?;
+
+    Ok(Json(item.into()))
+}
+
+async fn set_archived(
+    state: &AppState,
+    user_uuid: Uuid,
+    item_id: Uuid,
+    archived: bool,
+) -> AppResult<Item> {
+    let (existing_item, role) = item_access(state, user_uuid, item_id).await?;
+    require_item_write(&role)?;
+
+    if existing_item.archived_at.is_some() == archived {
+        return Ok(existing_item);
+    }
+
+    let item = sqlx::query_as::<_, Item>(
+        r#"
+        UPDATE items
+        SET archived_at = CASE WHEN $2 THEN COALESCE(archived_at, CURRENT_TIMESTAMP) END
+        WHERE id = $1
+        RETURNING *
+        "#,
+    )
+    .bind(item_id)
+    .bind(archived)
+    .fetch_one(&state.db)
+    .await?;
+
+    publish_item_event(
+        state,
+        ItemEvent::new(ItemEventKind::Updated, &item, user_uuid),
+    )
+    .await;
+
+    Ok(item)
+}
+
 pub async fn delete_item(
     State(state): State<AppState>,
-    user_id: axum::Extension<String>,
+    AuthUser(user_uuid): AuthUser,
     Path(item_id): Path<Uuid>,
 ) -> AppResult<StatusCode> {
-    let user_uuid: Uuid = user_id
-        .0
-        .parse()
-        .map_err(|_| AppError::Internal("Invalid user ID format".to_string()))?;
+    remove_item(&state, user_uuid, item_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Deletes a single item the user may change, announcing it to subscribers.
+async fn remove_item(state: &AppState, user_uuid: Uuid, item_id: Uuid) -> AppResult<()> {
+    let (item, role) = item_access(state, user_uuid, item_id).await?;
+    require_item_write(&role)?;
 
-    let result = sqlx::query("DELETE FROM items WHERE id = $1 AND user_id = $2")
+    let attachment_keys = attachment_keys(state, &[item_id]).await?;
+    let result = sqlx::query("DELETE FROM items WHERE id = $1")
         .bind(item_id)
-        .bind(user_uuid)
         .execute(&state.db)
         .await?;
 
     if result.rows_affected() == 0 {
         return Err(AppError::NotFound("Item not found".to_string()));
     }
+    remove_attachment_blobs(state, attachment_keys).await;
 
-    Ok(StatusCode::NO_CONTENT)
+    publish_item_event(
+        state,
+        ItemEvent::new(ItemEventKind::Deleted, &item, user_uuid),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// Deletes several items. By default all must exist or none are deleted (`204`);
+/// with `?mode=partial` each is attempted independently (`207` with per-element results).
+pub async fn batch_delete_items(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Query(query): Query<BatchQuery>,
+    Json(payload): Json<BatchDeleteItems>,
+) -> AppResult<Response> {
+    payload.validate()?;
+
+    if query.mode == BatchMode::Partial {
+        let mut results = Vec::with_capacity(payload.ids.len());
+        for (index, item_id) in payload.ids.into_iter().enumerate() {
+            let result = match remove_item(&state, user_uuid, item_id).await {
+                Ok(()) => BatchItemResult::<ItemResponse> {
+                    index,
+                    status: StatusCode::NO_CONTENT.as_u16(),
+                    id: Some(item_id),
+                    item: None,
+                    error: None,
+                },
+                Err(e) => BatchItemResult::failed(index, Some(item_id), e),
+            };
+            results.push(result);
+        }
+
+        return Ok((StatusCode::MULTI_STATUS, Json(results)).into_response());
+    }
+
+    // Ids in workspaces where the user can't change items count as missing
+    let attachment_keys = attachment_keys(&state, &payload.ids).await?;
+    let mut tx = state.db.begin().await?;
+    let deleted = sqlx::query_as::<_, Item>(
+        r#"
+        DELETE FROM items
+        WHERE id = ANY($1) AND workspace_id IN (
+            SELECT workspace_id FROM workspace_members
+            WHERE user_id = $2 AND role IN ('owner', 'editor')
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(&payload.ids)
+    .bind(user_uuid)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let missing: Vec<String> = payload
+        .ids
+        .iter()
+        .filter(|id| !deleted.iter().any(|item| item.id == **id))
+        .map(Uuid::to_string)
+        .collect();
+    if !missing.is_empty() {
+        // Dropping the transaction rolls back the deletes that did match
+        return Err(AppError::NotFound(format!(
+            "Items not found: {}",
+            missing.join(", ")
+        )));
+    }
+    tx.commit().await?;
+    remove_attachment_blobs(&state, attachment_keys).await;
+
+    for item in &deleted {
+        publish_item_event(
+            &state,
+            ItemEvent::new(ItemEventKind::Deleted, item, user_uuid),
+        )
+        .await;
+    }
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+/// Sets the status of several items in one statement. Ids the caller can't
+/// change, items already in that status, and (when transition rules
+/// are enforced) items that may not move to it are skipped; only the number of
+/// items actually changed is returned.
+pub async fn bulk_update_status(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Json(payload): Json<BulkStatusUpdate>,
+) -> AppResult<Json<BulkStatusResponse>> {
+    payload.validate()?;
+    let status = prepare_status(&payload.status)?;
+
+    // NULL means any current status may move to the target
+    let allowed_from = state
+        .config
+        .enforce_status_transitions
+        .then(|| statuses_allowed_into(&status));
+
+    let updated = sqlx::query_as::<_, Item>(
+        r#"
+        UPDATE items SET status = $3
+        WHERE id = ANY($1) AND status IS DISTINCT FROM $3
+          AND ($4::TEXT[] IS NULL OR status = ANY($4))
+          AND workspace_id IN (
+              SELECT workspace_id FROM workspace_members
+              WHERE user_id = $2 AND role IN ('owner', 'editor')
+          )
+        RETURNING *
+        "#,
+    )
+    .bind(&payload.ids)
+    .bind(user_uuid)
+    .bind(&status)
+    .bind(allowed_from)
+    .fetch_all(&state.db)
+    .await?;
+
+    for item in &updated {
+        publish_item_event(
+            &state,
+            ItemEvent::new(ItemEventKind::Updated, item, user_uuid),
+        )
+        .await;
+    }
+
+    Ok(Json(BulkStatusResponse {
+        updated: updated.len() as u64,
+    }))
+}
+
+/// Puts the given items in the requested order for `?sort=position`. The items
+/// trade the positions they already hold, so items left out keep their places and
+/// one page of a longer list can be reordered on its own. Returns the items in
+/// their new order.
+pub async fn reorder_items(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Json(payload): Json<ReorderItems>,
+) -> AppResult<Json<Vec<ItemResponse>>> {
+    payload.validate()?;
+    let mut seen = HashSet::with_capacity(payload.ids.len());
+    if let Some(repeated) = payload.ids.iter().find(|id| !seen.insert(**id)) {
+        return Err(AppError::Validation(format!(
+            "Item {} is listed more than once",
+            repeated
+        )));
+    }
+
+    // Locking in id order makes overlapping reorders wait for each other rather
+    // than deadlock; the one that waited then sees the positions the first left
+    let mut tx = state.db.begin().await?;
+    let current: Vec<(Uuid, i64)> = sqlx::query_as(
+        r#"
+        SELECT id, position FROM items
+        WHERE id = ANY($1) AND workspace_id IN (
+            SELECT workspace_id FROM workspace_members
+            WHERE user_id = $2 AND role IN ('owner', 'editor')
+        )
+        ORDER BY id
+        FOR UPDATE
+        "#,
+    )
+    .bind(&payload.ids)
+    .bind(user_uuid)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    // Ids in workspaces where the user can't change items count as missing
+    let missing: Vec<String> = payload
+        .ids
+        .iter()
+        .filter(|id| !current.iter().any(|(found, _)| found == *id))
+        .map(Uuid::to_string)
+        .collect();
+    if !missing.is_empty() {
+        return Err(AppError::NotFound(format!(
+            "Items not found: {}",
+            missing.join(", ")
+        )));
+    }
+
+    let mut positions: Vec<i64> = current.iter().map(|(_, position)| *position).collect();
+    positions.sort_unstable();
+
+    let moved = sqlx::query_as::<_, Item>(
+        r#"
+        UPDATE items SET position = moved.position
+        FROM UNNEST($1::UUID[], $2::BIGINT[]) AS moved(id, position)
+        WHERE items.id = moved.id AND items.position <> moved.position
+        RETURNING items.*
+        "#,
+    )
+    .bind(&payload.ids)
+    .bind(&positions)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let items =
+        sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ANY($1) ORDER BY position")
+            .bind(&payload.ids)
+            .fetch_all(&mut *tx)
+            .await?;
+    tx.commit().await?;
+
+    for item in &moved {
+        publish_item_event(
+            &state,
+            ItemEvent::new(ItemEventKind::Updated, item, user_uuid),
+        )
+        .await;
+    }
+
+    Ok(Json(items.into_iter().map(ItemResponse::from).collect()))
+}
+
+/// Streams changes to items in the caller's workspaces as server-sent events. Each
+/// event is named after its kind (`created`, `updated`, `deleted`); a `lagged` event
+/// means some were missed and the client should refetch. Workspaces joined after the
+/// stream opens take effect on reconnect; leaving one stops its events right away.
+pub async fn item_events(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let workspace_ids = member_workspace_ids(&state, user_uuid).await?;
+    let events = state.item_events.subscribe();
+    let (tx, rx) = mpsc::channel(ITEM_EVENTS_BUFFER);
+    // The forwarder outlives this handler, so it carries the request's timestamp format along
+    let format = timestamp::current_format();
+    tokio::spawn(with_timestamp_format(
+        format,
+        forward_item_events(
+            state,
+            user_uuid,
+            workspace_ids.into_iter().collect(),
+            events,
+            tx,
+        ),
+    ));
+
+    Ok(Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default()))
+}
+
+/// Events `forward_item_events` may queue ahead of a slow client.
+const ITEM_EVENTS_BUFFER: usize = 16;
+
+/// Feeds one `/items/events` subscriber until the client goes away or the server
+/// shuts down, which ends the stream so it doesn't hold up the graceful drain.
+/// Membership is checked again before each event, so someone removed from a
+/// workspace stops hearing about it.
+async fn forward_item_events(
+    state: AppState,
+    user_uuid: Uuid,
+    mut workspace_ids: HashSet<Uuid>,
+    mut events: broadcast::Receiver<ItemEvent>,
+    tx: mpsc::Sender<Result<Event, Infallible>>,
+) {
+    let mut shutdown = state.shutdown.clone();
+    if *shutdown.borrow() {
+        return;
+    }
+
+    loop {
+        let received = tokio::select! {
+            received = events.recv() => received,
+            _ = shutdown.changed() => break,
+            _ = tx.closed() => break,
+        };
+
+        let event = match received {
+            Ok(event) if workspace_ids.contains(&event.workspace_id) => {
+                match is_workspace_member(&state, user_uuid, event.workspace_id).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        workspace_ids.remove(&event.workspace_id);
+                        continue;
+                    }
+                    // The client reconnects and gets a fresh membership read
+                    Err(e) => {
+                        tracing::warn!("Ending item event stream: {:?}", e);
+                        break;
+                    }
+                }
+
+                let kind = match event.kind {
+                    ItemEventKind::Created => "created",
+                    ItemEventKind::Updated => "updated",
+                    ItemEventKind::Deleted => "deleted",
+                };
+                match Event::default().event(kind).json_data(&event) {
+                    Ok(event) => event,
+                    Err(_) => continue,
+                }
+            }
+            Ok(_) => continue,
+            Err(RecvError::Lagged(missed)) => {
+                Event::default().event("lagged").data(missed.to_string())
+            }
+            Err(RecvError::Closed) => break,
+        };
+
+        if tx.send(Ok(event)).await.is_err() {
+            break;
+        }
+    }
+}
+
+async fn is_workspace_member(
+    state: &AppState,
+    user_uuid: Uuid,
+    workspace_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM workspace_members WHERE workspace_id = $1 AND user_id = $2)",
+    )
+    .bind(workspace_id)
+    .bind(user_uuid)
+    .fetch_one(&state.db)
+    .await
 }
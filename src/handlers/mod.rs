@@ -1,7 +1,21 @@
+pub mod admin;
+pub mod api_keys;
+pub mod attachments;
 pub mod auth;
+pub mod comments;
+pub mod export;
 pub mod items;
 pub mod health;
+pub mod tags;
+pub mod workspaces;
 
+pub use admin::*;
+pub use api_keys::*;
+pub use attachments::*;
 pub use auth::*;
+pub use comments::*;
+pub use export::*;
 pub use items::*;
 pub use health::*;
+pub use tags::*;
+pub use workspaces::*;
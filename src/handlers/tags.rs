@@ -0,0 +1,111 @@
+use axum::extract::{OriginalUri, State};
+use validator::Validate;
+
+use crate::{
+    error::{AppError, AppResult},
+    events::{publish_item_event, ItemEvent, ItemEventKind},
+    extract::{AuthUser, Json, Query},
+    models::{
+        normalize_tag, Item, ListParams, Page, PageLinks, RenameTag, RenameTagResponse, TagCount,
+        DEFAULT_TAG_SORT, TAG_SORT_FIELDS,
+    },
+    AppState,
+};
+
+/// The distinct tags on items in the user's workspaces, with how many items carry
+/// each; most used first unless `?sort=tag`.
+pub async fn get_tags(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    OriginalUri(uri): OriginalUri,
+    Query(params): Query<ListParams>,
+) -> AppResult<Json<Page<TagCount>>> {
+    params.validate()?;
+
+    let order_by = params.order_by(TAG_SORT_FIELDS, DEFAULT_TAG_SORT)?;
+
+    let total: i64 = sqlx::query_scalar(
+        r#"
+        SELECT COUNT(DISTINCT tag)
+        FROM items, unnest(items.tags) AS tag
+        WHERE items.workspace_id IN (SELECT workspace_id FROM workspace_members WHERE user_id = $1)
+        "#,
+    )
+    .bind(user_uuid)
+    .fetch_one(&state.db)
+    .await?;
+
+    // `order_by` only ever contains allowlisted columns; `tag` keeps pages stable on ties
+    let tags = sqlx::query_as::<_, TagCount>(&format!(
+        "SELECT tag, COUNT(*) AS count \
+         FROM items, unnest(items.tags) AS tag \
+         WHERE items.workspace_id IN \
+               (SELECT workspace_id FROM workspace_members WHERE user_id = $1) \
+         GROUP BY tag \
+         ORDER BY {}, tag LIMIT $2 OFFSET $3",
+        order_by
+    ))
+    .bind(user_uuid)
+    .bind(params.per_page)
+    .bind(params.offset())
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(Page {
+        data: tags,
+        page: params.page,
+        per_page: params.per_page,
+        total,
+        links: PageLinks::new(&uri, &params, total),
+    }))
+}
+
+/// Renames a tag on every item the caller created and may still change. Items that
+/// already carry the new name just lose the old one, merging the two tags.
+pub async fn rename_tag(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Json(payload): Json<RenameTag>,
+) -> AppResult<Json<RenameTagResponse>> {
+    payload.validate()?;
+
+    let from = normalize_tag(&payload.from);
+    let to = normalize_tag(&payload.to);
+    if from == to {
+        return Err(AppError::Validation(
+            "from and to must be different tags".to_string(),
+        ));
+    }
+
+    // A single statement, so every matching item is renamed or none is
+    let updated = sqlx::query_as::<_, Item>(
+        r#"
+        UPDATE items
+        SET tags = CASE WHEN $2 = ANY(tags) THEN array_remove(tags, $1)
+                        ELSE array_replace(tags, $1, $2) END
+        WHERE $1 = ANY(tags) AND user_id = $3
+          AND workspace_id IN (
+              SELECT workspace_id FROM workspace_members
+              WHERE user_id = $3 AND role IN ('owner', 'editor')
+          )
+        RETURNING *
+        "#,
+    )
+    .bind(&from)
+    .bind(&to)
+    .bind(user_uuid)
+    .fetch_all(&state.db)
+    .await?;
+
+    for item in &updated {
+        publish_item_event(
+            &state,
+            ItemEvent::new(ItemEventKind::Updated, item, user_uuid),
+        )
+        .await;
+    }
+
+    Ok(Json(RenameTagResponse {
+        updated: updated.len() as u64,
+    }))
+}
@@ -0,0 +1,360 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use sqlx::{FromRow, Row};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::{AppError, AppResult},
+    extract::{AuthUser, Json},
+    models::{
+        can_write_items, AddWorkspaceMember, CreateWorkspace, Item, UpdateWorkspaceMember,
+        WorkspaceMemberResponse, WorkspaceResponse, WORKSPACE_OWNER, WORKSPACE_ROLES,
+    },
+    AppState,
+};
+
+pub async fn create_workspace(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Json(payload): Json<CreateWorkspace>,
+) -> AppResult<(StatusCode, Json<WorkspaceResponse>)> {
+    payload.validate()?;
+
+    let mut tx = state.db.begin().await?;
+    let (id, created_at) = sqlx::query_as::<_, (Uuid, chrono::DateTime<chrono::Utc>)>(
+        "INSERT INTO workspaces (name) VALUES ($1) RETURNING id, created_at",
+    )
+    .bind(&payload.name)
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query("INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $2, $3)")
+        .bind(id)
+        .bind(user_uuid)
+        .bind(WORKSPACE_OWNER)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WorkspaceResponse {
+            id,
+            name: payload.name,
+            personal: false,
+            role: WORKSPACE_OWNER.to_string(),
+            created_at,
+        }),
+    ))
+}
+
+/// Lists the workspaces the caller belongs to, personal workspace first.
+pub async fn get_workspaces(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+) -> AppResult<Json<Vec<WorkspaceResponse>>> {
+    let workspaces = sqlx::query_as::<_, WorkspaceResponse>(
+        r#"
+        SELECT workspaces.id, workspaces.name, workspaces.personal_user_id IS NOT NULL AS personal,
+               members.role, workspaces.created_at
+        FROM workspaces
+        JOIN workspace_members members ON members.workspace_id = workspaces.id
+        WHERE members.user_id = $1
+        ORDER BY personal DESC, workspaces.created_at ASC
+        "#,
+    )
+    .bind(user_uuid)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(workspaces))
+}
+
+pub async fn get_workspace_members(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(workspace_id): Path<Uuid>,
+) -> AppResult<Json<Vec<WorkspaceMemberResponse>>> {
+    workspace_role(&state, user_uuid, workspace_id).await?;
+
+    let members = sqlx::query_as::<_, WorkspaceMemberResponse>(
+        r#"
+        SELECT members.user_id, users.username, members.role, members.created_at
+        FROM workspace_members members
+        JOIN users ON users.id = members.user_id
+        WHERE members.workspace_id = $1
+        ORDER BY members.created_at ASC
+        "#,
+    )
+    .bind(workspace_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(members))
+}
+
+/// Adds an existing user to a shared workspace by email. Owners only.
+pub async fn add_workspace_member(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path(workspace_id): Path<Uuid>,
+    Json(payload): Json<AddWorkspaceMember>,
+) -> AppResult<(StatusCode, Json<WorkspaceMemberResponse>)> {
+    payload.validate()?;
+    validate_role(&payload.role)?;
+
+    require_shared_workspace_owner(&state, user_uuid, workspace_id).await?;
+
+    let (member_id, username): (Uuid, String) =
+        sqlx::query_as("SELECT id, username FROM users WHERE email = $1")
+            .bind(&payload.email)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let created_at: Option<chrono::DateTime<chrono::Utc>> = sqlx::query_scalar(
+        r#"
+        INSERT INTO workspace_members (workspace_id, user_id, role) VALUES ($1, $2, $3)
+        ON CONFLICT (workspace_id, user_id) DO NOTHING
+        RETURNING created_at
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(member_id)
+    .bind(&payload.role)
+    .fetch_optional(&state.db)
+    .await?;
+    let created_at = created_at.ok_or_else(|| {
+        AppError::UnprocessableEntity("User is already a member of this workspace".to_string())
+    })?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(WorkspaceMemberResponse {
+            user_id: member_id,
+            username,
+            role: payload.role,
+            created_at,
+        }),
+    ))
+}
+
+/// Changes a member's role. Owners only; the last owner can't step down.
+pub async fn update_workspace_member(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path((workspace_id, member_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateWorkspaceMember>,
+) -> AppResult<Json<WorkspaceMemberResponse>> {
+    validate_role(&payload.role)?;
+
+    require_shared_workspace_owner(&state, user_uuid, workspace_id).await?;
+
+    let mut tx = state.db.begin().await?;
+    if payload.role != WORKSPACE_OWNER {
+        ensure_other_owner(&mut tx, workspace_id, member_id).await?;
+    }
+    let member = sqlx::query_as::<_, WorkspaceMemberResponse>(
+        r#"
+        UPDATE workspace_members members SET role = $3
+        FROM users
+        WHERE members.workspace_id = $1 AND members.user_id = $2 AND users.id = members.user_id
+        RETURNING members.user_id, users.username, members.role, members.created_at
+        "#,
+    )
+    .bind(workspace_id)
+    .bind(member_id)
+    .bind(&payload.role)
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Member not found".to_string()))?;
+    tx.commit().await?;
+
+    Ok(Json(member))
+}
+
+/// Removes a member. Owners may remove anyone and members may remove
+/// themselves, as long as an owner remains.
+pub async fn remove_workspace_member(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    Path((workspace_id, member_id)): Path<(Uuid, Uuid)>,
+) -> AppResult<StatusCode> {
+    if member_id == user_uuid {
+        workspace_role(&state, user_uuid, workspace_id).await?;
+        ensure_shared(&state, workspace_id).await?;
+    } else {
+        require_shared_workspace_owner(&state, user_uuid, workspace_id).await?;
+    }
+
+    let mut tx = state.db.begin().await?;
+    ensure_other_owner(&mut tx, workspace_id, member_id).await?;
+    let result =
+        sqlx::query("DELETE FROM workspace_members WHERE workspace_id = $1 AND user_id = $2")
+            .bind(workspace_id)
+            .bind(member_id)
+            .execute(&mut *tx)
+            .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Member not found".to_string()));
+    }
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Loads an item from any workspace the user belongs to, with the user's role there.
+pub(crate) async fn item_access(
+    state: &AppState,
+    user_uuid: Uuid,
+    item_id: Uuid,
+) -> AppResult<(Item, String)> {
+    let row = sqlx::query(
+        r#"
+        SELECT items.*, members.role
+        FROM items
+        JOIN workspace_members members
+          ON members.workspace_id = items.workspace_id AND members.user_id = $2
+        WHERE items.id = $1
+        "#,
+    )
+    .bind(item_id)
+    .bind(user_uuid)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Item not found".to_string()))?;
+
+    Ok((Item::from_row(&row)?, row.try_get("role")?))
+}
+
+pub(crate) fn require_item_write(role: &str) -> AppResult<()> {
+    if !can_write_items(role) {
+        return Err(AppError::Forbidden(format!(
+            "The '{}' workspace role cannot change items",
+            role
+        )));
+    }
+
+    Ok(())
+}
+
+/// The workspace a new item goes into: the one requested, if the user may add
+/// items to it, or else the user's personal workspace.
+pub(crate) async fn target_workspace(
+    state: &AppState,
+    user_uuid: Uuid,
+    workspace_id: Option<Uuid>,
+) -> AppResult<Uuid> {
+    match workspace_id {
+        Some(workspace_id) => {
+            require_item_write(&workspace_role(state, user_uuid, workspace_id).await?)?;
+            Ok(workspace_id)
+        }
+        None => {
+            let personal =
+                sqlx::query_scalar("SELECT id FROM workspaces WHERE personal_user_id = $1")
+                    .bind(user_uuid)
+                    .fetch_one(&state.db)
+                    .await?;
+            Ok(personal)
+        }
+    }
+}
+
+/// Ids of every workspace the user belongs to.
+pub(crate) async fn member_workspace_ids(
+    state: &AppState,
+    user_uuid: Uuid,
+) -> AppResult<Vec<Uuid>> {
+    let ids = sqlx::query_scalar("SELECT workspace_id FROM workspace_members WHERE user_id = $1")
+        .bind(user_uuid)
+        .fetch_all(&state.db)
+        .await?;
+
+    Ok(ids)
+}
+
+/// The user's role in the workspace; non-members get a 404 so workspaces they
+/// aren't part of stay invisible.
+async fn workspace_role(
+    state: &AppState,
+    user_uuid: Uuid,
+    workspace_id: Uuid,
+) -> AppResult<String> {
+    sqlx::query_scalar(
+        "SELECT role FROM workspace_members WHERE workspace_id = $1 AND user_id = $2",
+    )
+    .bind(workspace_id)
+    .bind(user_uuid)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Workspace not found".to_string()))
+}
+
+async fn require_shared_workspace_owner(
+    state: &AppState,
+    user_uuid: Uuid,
+    workspace_id: Uuid,
+) -> AppResult<()> {
+    if workspace_role(state, user_uuid, workspace_id).await? != WORKSPACE_OWNER {
+        return Err(AppError::Forbidden(
+            "Only workspace owners can manage members".to_string(),
+        ));
+    }
+
+    ensure_shared(state, workspace_id).await
+}
+
+/// Personal workspaces always have exactly their user as the only member.
+async fn ensure_shared(state: &AppState, workspace_id: Uuid) -> AppResult<()> {
+    let personal: bool =
+        sqlx::query_scalar("SELECT personal_user_id IS NOT NULL FROM workspaces WHERE id = $1")
+            .bind(workspace_id)
+            .fetch_one(&state.db)
+            .await?;
+    if personal {
+        return Err(AppError::UnprocessableEntity(
+            "Personal workspaces cannot be shared".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fails when `member_id` is the workspace's only owner. Locks the owner rows
+/// so two owners can't demote each other at the same time.
+async fn ensure_other_owner(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    workspace_id: Uuid,
+    member_id: Uuid,
+) -> AppResult<()> {
+    let owners: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT user_id FROM workspace_members WHERE workspace_id = $1 AND role = $2 FOR UPDATE",
+    )
+    .bind(workspace_id)
+    .bind(WORKSPACE_OWNER)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    if owners == [member_id] {
+        return Err(AppError::UnprocessableEntity(
+            "A workspace must keep at least one owner".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn validate_role(role: &str) -> AppResult<()> {
+    if !WORKSPACE_ROLES.contains(&role) {
+        return Err(AppError::Validation(format!(
+            "Unknown role '{}'. Allowed roles: {}",
+            role,
+            WORKSPACE_ROLES.join(", ")
+        )));
+    }
+
+    Ok(())
+}
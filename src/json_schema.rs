@@ -0,0 +1,89 @@
+use anyhow::Context;
+use jsonschema::{paths::PathChunk, JSONSchema};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::{config::Config, error::FieldError};
+
+/// JSON Schemas from `JSON_SCHEMAS`, compiled once at startup and keyed by
+/// `METHOD /path` as the route was registered (e.g. `PATCH /items/:id`).
+/// They add checks `validator` attributes can't express; bodies are checked
+/// before they are deserialized into the handler's type.
+pub struct SchemaRegistry {
+    schemas: HashMap<String, JSONSchema>,
+}
+
+impl SchemaRegistry {
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let mut schemas = HashMap::new();
+        for (route, file) in &config.json_schemas {
+            let text = std::fs::read_to_string(file)
+                .with_context(|| format!("Could not read JSON schema {file:?} for {route}"))?;
+            let schema: Value = serde_json::from_str(&text)
+                .with_context(|| format!("JSON schema {file:?} for {route} is not valid JSON"))?;
+            let compiled = JSONSchema::compile(&schema)
+                .map_err(|e| anyhow::anyhow!("JSON schema {file:?} for {route} is invalid: {e}"))?;
+            schemas.insert(route.clone(), compiled);
+        }
+
+        if !schemas.is_empty() {
+            tracing::info!("✅ Loaded {} JSON schemas", schemas.len());
+        }
+
+        Ok(Self { schemas })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty()
+    }
+
+    /// Checks `body` against the schema registered for `method` and `path`, if
+    /// any, returning every mismatch.
+    pub fn check(&self, method: &str, path: &str, body: &Value) -> Result<(), Vec<FieldError>> {
+        let Some(schema) = self.schemas.get(&format!("{} {}", method, path)) else {
+            return Ok(());
+        };
+
+        schema.validate(body).map_err(|errors| {
+            errors
+                .map(|error| FieldError {
+                    field: field_name(&error.instance_path),
+                    pointer: error.instance_path.to_string(),
+                    // The failing keyword, e.g. `required` or `maxLength`
+                    code: error
+                        .schema_path
+                        .clone()
+                        .into_vec()
+                        .pop()
+                        .unwrap_or_else(|| "schema".to_string()),
+                    message: error.to_string(),
+                })
+                .collect()
+        })
+    }
+}
+
+/// Dotted path of a location in the body, like `tags[0]`; `.` for the body itself.
+fn field_name(pointer: &jsonschema::paths::JSONPointer) -> String {
+    let mut field = String::new();
+    for chunk in pointer.iter() {
+        match chunk {
+            PathChunk::Index(index) => field.push_str(&format!("[{}]", index)),
+            PathChunk::Property(name) => push_segment(&mut field, name),
+            PathChunk::Keyword(keyword) => push_segment(&mut field, keyword),
+        }
+    }
+
+    if field.is_empty() {
+        ".".to_string()
+    } else {
+        field
+    }
+}
+
+fn push_segment(field: &mut String, name: &str) {
+    if !field.is_empty() {
+        field.push('.');
+    }
+    field.push_str(name);
+}
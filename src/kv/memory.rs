@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::{KeyValueStore, KvError};
+
+/// Entries kept before expired ones are swept out on insert.
+const MAX_MEMORY_ENTRIES: usize = 100_000;
+
+/// Keeps entries in this process. Nothing is shared with other instances, so
+/// behind a load balancer every instance has its own counters and cache.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, (Instant, Vec<u8>)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn insert(
+        entries: &mut HashMap<String, (Instant, Vec<u8>)>,
+        key: &str,
+        entry: (Instant, Vec<u8>),
+    ) {
+        if entries.len() >= MAX_MEMORY_ENTRIES && !entries.contains_key(key) {
+            let now = Instant::now();
+            entries.retain(|_, (expires_at, _)| *expires_at > now);
+            if entries.len() >= MAX_MEMORY_ENTRIES {
+                entries.clear();
+            }
+        }
+        entries.insert(key.to_string(), entry);
+    }
+}
+
+#[async_trait]
+impl KeyValueStore for MemoryStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, KvError> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(entries
+            .get(key)
+            .filter(|(expires_at, _)| *expires_at > Instant::now())
+            .map(|(_, value)| value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), KvError> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        Self::insert(&mut entries, key, (Instant::now() + ttl, value.to_vec()));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), KvError> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(key);
+        Ok(())
+    }
+
+    async fn increment(&self, key: &str, window: Duration) -> Result<(u64, Duration), KvError> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Counters are stored as decimal strings, as Redis does
+        let (expires_at, count) = match entries.get(key) {
+            Some((expires_at, value)) if *expires_at > now => {
+                let count: u64 = std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| KvError::Backend(format!("{} is not a counter", key)))?;
+                (*expires_at, count + 1)
+            }
+            _ => (now + window, 1),
+        };
+        Self::insert(
+            &mut entries,
+            key,
+            (expires_at, count.to_string().into_bytes()),
+        );
+
+        Ok((count, expires_at - now))
+    }
+}
@@ -0,0 +1,46 @@
+pub mod memory;
+pub mod redis;
+
+pub use memory::*;
+pub use redis::*;
+
+use async_trait::async_trait;
+use std::{sync::Arc, time::Duration};
+
+use crate::config::Config;
+
+#[derive(Debug, thiserror::Error)]
+pub enum KvError {
+    #[error("Key-value store error: {0}")]
+    Backend(String),
+}
+
+/// Expiring key-value storage for state that has to be shared between
+/// instances, such as rate limit counters and cached rows. Every entry expires.
+#[async_trait]
+pub trait KeyValueStore: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, KvError>;
+
+    /// Stores `value` under `key` for `ttl`, replacing any existing entry.
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), KvError>;
+
+    /// Removes the entry; deleting a missing key is not an error.
+    async fn delete(&self, key: &str) -> Result<(), KvError>;
+
+    /// Increments the counter under `key`, starting it at 1 with a lifetime of
+    /// `window` when missing. Returns the new count and the time until the
+    /// counter expires.
+    async fn increment(&self, key: &str, window: Duration) -> Result<(u64, Duration), KvError>;
+}
+
+/// Connects to `REDIS_URL` when set, otherwise keeps everything in this process.
+pub async fn from_config(config: &Config) -> anyhow::Result<Arc<dyn KeyValueStore>> {
+    match &config.redis_url {
+        Some(url) => {
+            let store = RedisStore::connect(url).await?;
+            tracing::info!("✅ Connected to Redis");
+            Ok(Arc::new(store))
+        }
+        None => Ok(Arc::new(MemoryStore::new())),
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands, RedisError};
+use std::time::Duration;
+
+use super::{KeyValueStore, KvError};
+
+impl From<RedisError> for KvError {
+    fn from(err: RedisError) -> Self {
+        KvError::Backend(err.to_string())
+    }
+}
+
+/// Keeps entries in Redis so every instance shares them. The connection is
+/// multiplexed and reconnects on its own after failures.
+#[derive(Clone)]
+pub struct RedisStore {
+    conn: ConnectionManager,
+}
+
+impl RedisStore {
+    pub async fn connect(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url).context("REDIS_URL must be a valid Redis URL")?;
+        let conn = ConnectionManager::new(client)
+            .await
+            .context("Failed to connect to Redis")?;
+
+        Ok(Self { conn })
+    }
+}
+
+#[async_trait]
+impl KeyValueStore for RedisStore {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, KvError> {
+        let mut conn = self.conn.clone();
+        Ok(conn.get(key).await?)
+    }
+
+    async fn set(&self, key: &str, value: &[u8], ttl: Duration) -> Result<(), KvError> {
+        let mut conn = self.conn.clone();
+        // PX takes milliseconds and rejects 0
+        let ttl_ms = ttl.as_millis().max(1) as u64;
+        redis::cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async::<_, ()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), KvError> {
+        let mut conn = self.conn.clone();
+        conn.del::<_, ()>(key).await?;
+        Ok(())
+    }
+
+    async fn increment(&self, key: &str, window: Duration) -> Result<(u64, Duration), KvError> {
+        let mut conn = self.conn.clone();
+        let window_ms = window.as_millis().max(1) as u64;
+        // Create the counter with its expiry first so INCR never leaves one without
+        let (count, ttl_ms): (u64, i64) = redis::pipe()
+            .atomic()
+            .cmd("SET")
+            .arg(key)
+            .arg(0)
+            .arg("PX")
+            .arg(window_ms)
+            .arg("NX")
+            .ignore()
+            .incr(key, 1)
+            .pttl(key)
+            .query_async(&mut conn)
+            .await?;
+
+        let remaining = if ttl_ms > 0 {
+            Duration::from_millis(ttl_ms as u64)
+        } else {
+            window
+        };
+        Ok((count, remaining))
+    }
+}
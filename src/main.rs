@@ -1,61 +1,223 @@
+mod cache;
+mod circuit_breaker;
+mod cli;
 mod config;
 mod db;
+mod diagnostics;
 mod error;
+mod events;
+mod extract;
 mod handlers;
+mod json_schema;
+mod kv;
 mod middleware;
 mod models;
+mod query_count;
+mod rate_limit;
 mod routes;
+mod single_flight;
+mod storage;
+mod tasks;
+#[cfg(test)]
+mod test_support;
 mod utils;
 
+use anyhow::Context;
 use config::Config;
 use sqlx::PgPool;
-use std::net::SocketAddr;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64},
+        Arc,
+    },
+};
+use tokio::sync::{broadcast, watch};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer};
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
     pub config: Config,
+    pub maintenance: Arc<AtomicBool>,
+    pub item_events: broadcast::Sender<events::ItemEvent>,
+    pub user_cache: Arc<cache::UserCache>,
+    pub storage: Arc<dyn storage::Storage>,
+    pub item_list_flight: Arc<single_flight::ItemListFlight>,
+    pub user_rate_limiter: Arc<rate_limit::RateLimiter>,
+    pub export_rate_limiter: Arc<rate_limit::RateLimiter>,
+    pub kv: Arc<dyn kv::KeyValueStore>,
+    pub db_breaker: Arc<circuit_breaker::CircuitBreaker>,
+    /// Requests turned away because the request queue was full.
+    pub shed_requests: Arc<AtomicU64>,
+    pub json_schemas: Arc<json_schema::SchemaRegistry>,
+    /// Flips to `true` on shutdown; long-lived responses end when it does.
+    pub shutdown: watch::Receiver<bool>,
+}
+
+impl AppState {
+    pub async fn new(
+        config: Config,
+        db: PgPool,
+        item_events: broadcast::Sender<events::ItemEvent>,
+        shutdown: watch::Receiver<bool>,
+    ) -> anyhow::Result<Self> {
+        // Rate limit counters and cached rows; shared between instances with Redis
+        let kv = kv::from_config(&config).await?;
+
+        Ok(Self {
+            db,
+            maintenance: Arc::new(AtomicBool::new(config.maintenance_mode)),
+            item_events,
+            user_cache: Arc::new(cache::UserCache::new(config.cache_user_ttl_secs, kv.clone())),
+            storage: storage::from_config(&config)?,
+            item_list_flight: Arc::new(single_flight::SingleFlight::new(
+                config.single_flight_enabled,
+            )),
+            user_rate_limiter: Arc::new(rate_limit::RateLimiter::new(
+                "user",
+                config.user_rate_limit,
+                config.user_rate_limit_window_secs,
+                kv.clone(),
+            )),
+            export_rate_limiter: Arc::new(rate_limit::RateLimiter::new(
+                "export",
+                u32::from(config.data_export_window_secs > 0),
+                config.data_export_window_secs,
+                kv.clone(),
+            )),
+            kv,
+            db_breaker: Arc::new(circuit_breaker::CircuitBreaker::new(
+                config.db_breaker_threshold,
+                config.db_breaker_cooldown_secs,
+            )),
+            shed_requests: Arc::new(AtomicU64::new(0)),
+            json_schemas: Arc::new(json_schema::SchemaRegistry::from_config(&config)?),
+            shutdown,
+            config,
+        })
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
+    // Load configuration (first, so the log filter can come from it)
+    let config = Config::from_env()?;
+
+    // Initialize tracing; RUST_LOG overrides the configured filter
+    let log_filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(&config.log_filter)
+            .with_context(|| format!("Invalid LOG_FILTER: {}", config.log_filter))?,
+    };
+    // The filter applies to the log output only, so the query counter still sees
+    // sqlx's statement events
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "rust_backend_starter=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_filter(log_filter))
+        .with(config.query_count_enabled.then(query_count::layer))
         .init();
 
     tracing::info!("🚀 Starting Rust Backend Starter...");
 
-    // Load configuration
-    let config = Config::from_env()?;
+    config.validate()?;
     tracing::info!("✅ Configuration loaded");
 
     // Create database connection pool
-    let db_pool = db::create_pool(&config.database_url).await?;
+    let db_pool = db::create_pool(&config).await?;
 
     // Run migrations
-    db::run_migrations(&db_pool).await?;
+    db::run_migrations(&db_pool, &config).await?;
+
+    // One-off commands (e.g. `create-admin`) run instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if cli::run(&args, &db_pool, &config).await? {
+        return Ok(());
+    }
+
+    // Item change notifications for `/items/events` subscribers
+    let (item_events, _) = broadcast::channel(events::ITEM_EVENTS_CAPACITY);
+
+    // Start background tasks; they stop when the shutdown flag flips
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut background_tasks = Vec::new();
+    background_tasks.extend(tasks::spawn_pool_stats(
+        db_pool.clone(),
+        config.pool_stats_interval_secs,
+        shutdown_rx.clone(),
+    ));
+    background_tasks.extend(tasks::spawn_cleanup(
+        db_pool.clone(),
+        config.cleanup_interval_secs,
+        shutdown_rx.clone(),
+    ));
+    background_tasks.extend(tasks::spawn_item_listener(
+        db_pool.clone(),
+        config.items_notify_enabled,
+        item_events.clone(),
+        shutdown_rx.clone(),
+    ));
+
+    let addr: SocketAddr = config
+        .server_address()
+        .parse()
+        .with_context(|| format!("Invalid listen address: {}", config.server_address()))?;
+
+    // Fail before serving if the setup can't work, and summarize it for operators
+    diagnostics::self_check(&config, &db_pool, addr).await?;
 
     // Create application state
-    let state = AppState {
-        db: db_pool,
-        config: config.clone(),
-    };
+    let state = AppState::new(config, db_pool, item_events, shutdown_rx).await?;
 
     // Create router
-    let app = routes::create_router(state, config.clone());
+    let app = routes::create_router(state);
 
     // Start server
-    let addr: SocketAddr = config.server_address().parse()?;
     tracing::info!("🌐 Server listening on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        shutdown_signal().await;
+        // Flipped before draining, so event streams end instead of holding it up
+        let _ = shutdown_tx.send(true);
+    })
+    .await?;
+
+    // Wait for background tasks before exiting
+    for task in background_tasks {
+        let _ = task.await;
+    }
+
+    tracing::info!("👋 Server shut down gracefully");
 
     Ok(())
 }
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    tracing::info!("🛑 Shutdown signal received");
+}
@@ -1,27 +1,343 @@
-use crate::{config::Config, error::AppError, utils::auth::verify_token};
-use axum::{extract::Request, http::header, middleware::Next, response::Response};
+use crate::{
+    error::{AppError, AppResult},
+    extract::AuthUser,
+    models::ROLE_ADMIN,
+    utils::{
+        auth::{client_fingerprint, hash_token, verify_token, Claims},
+        cookie::get_cookie,
+    },
+    AppState,
+};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tracing::Instrument;
+use uuid::Uuid;
 
-pub async fn auth_middleware(mut req: Request, next: Next) -> Result<Response, AppError> {
-    let auth_header = req
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// Correlation id set by a proxy or client; one is generated when absent.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+pub async fn auth_middleware(
+    State(state): State<AppState>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let (claims, user_id) = match req.headers().get(API_KEY_HEADER) {
+        Some(api_key) => {
+            let api_key = api_key.to_str().map_err(|_| {
+                AppError::Unauthorized("X-API-Key header contains invalid characters".to_string())
+            })?;
+            authenticate_api_key(&state, api_key).await?
+        }
+        None => authenticate_bearer(&state, req.headers()).await?,
+    };
+
+    // Everything logged while handling the request carries who made it. The span is
+    // attached to this request's future only, so it can't leak into other tasks
+    let request_id = req
         .headers()
-        .get(header::AUTHORIZATION)
-        .and_then(|h| h.to_str().ok())
-        .ok_or_else(|| AppError::Unauthorized("Missing authorization header".to_string()))?;
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let span = tracing::info_span!("auth", user_id = %user_id, request_id = %request_id);
 
-    let token = auth_header
-        .strip_prefix("Bearer ")
-        .ok_or_else(|| AppError::Unauthorized("Invalid authorization header format".to_string()))?;
+    // Add user ID and claims to request extensions for use in handlers
+    req.extensions_mut().insert(AuthUser(user_id));
+    req.extensions_mut().insert(claims);
 
-    let config = req
-        .extensions()
-        .get::<Config>()
-        .ok_or_else(|| AppError::Internal("Config not found in request extensions".to_string()))?;
+    Ok(next.run(req).instrument(span).await)
+}
+
+async fn authenticate_bearer(state: &AppState, headers: &HeaderMap) -> AppResult<(Claims, Uuid)> {
+    // The Authorization header wins; the auth cookie is only a fallback for browsers
+    let token = match headers.get(header::AUTHORIZATION) {
+        Some(auth_header) => bearer_token(auth_header)?,
+        None if state.config.auth_cookie_enabled => {
+            get_cookie(headers, &state.config.auth_cookie_name)
+                .filter(|token| !token.is_empty())
+                .ok_or_else(|| {
+                    AppError::Unauthorized(
+                        "Missing authorization header or auth cookie".to_string(),
+                    )
+                })?
+        }
+        None => {
+            return Err(AppError::Unauthorized(
+                "Missing authorization header".to_string(),
+            ))
+        }
+    };
+
+    // Checked before parsing so an enormous token costs nothing to refuse
+    if token.len() > state.config.jwt_max_length {
+        return Err(AppError::Unauthorized(format!(
+            "Token is longer than the {} byte limit",
+            state.config.jwt_max_length
+        )));
+    }
 
-    let claims = verify_token(token, config)
+    let mut claims = verify_token(token, &state.config)
         .map_err(|e| AppError::Authentication(format!("Invalid token: {}", e)))?;
 
-    // Add user ID to request extensions for use in handlers
-    req.extensions_mut().insert(claims.sub.clone());
+    // Tokens issued before binding was enabled carry no fingerprint and are refused too
+    if state.config.bind_token_to_client
+        && claims.cfp.as_deref() != Some(client_fingerprint(headers).as_str())
+    {
+        return Err(AppError::Unauthorized(
+            "Token was issued to a different client".to_string(),
+        ));
+    }
+
+    // Re-check the account on every request so deactivation and token
+    // invalidation take effect immediately
+    let user_id = if state.config.verify_user_on_request {
+        check_account(&state.db, &mut claims).await?
+    } else {
+        resolve_subject(&state.db, &claims).await?
+    };
+
+    Ok((claims, user_id))
+}
+
+/// The token in an `Authorization: Bearer <token>` header. Headers that aren't
+/// visible ASCII, use another scheme or carry no token each get their own 401.
+fn bearer_token(auth_header: &HeaderValue) -> AppResult<&str> {
+    let value = auth_header.to_str().map_err(|_| {
+        AppError::Unauthorized("Authorization header contains invalid characters".to_string())
+    })?;
+
+    // Trailing whitespace is stripped from header values, so "Bearer " arrives as "Bearer"
+    let token = match value.strip_prefix("Bearer") {
+        Some(rest) if rest.is_empty() || rest.starts_with(' ') => rest.trim(),
+        _ => {
+            return Err(AppError::Unauthorized(
+                "Invalid authorization header format, expected 'Bearer <token>'".to_string(),
+            ))
+        }
+    };
+
+    if token.is_empty() {
+        return Err(AppError::Unauthorized(
+            "Authorization header carries no token".to_string(),
+        ));
+    }
+
+    Ok(token)
+}
+
+/// The user a token was issued to: its `uid`, else the id or email in `sub`.
+/// Subjects of either kind are accepted whatever `JWT_SUBJECT` says, so tokens
+/// issued before it changed keep working.
+///
+/// An email may since have moved to a newer account, so tokens without `uid`
+/// only resolve by email to an account that existed when they were issued.
+pub async fn resolve_subject(db: &PgPool, claims: &Claims) -> AppResult<Uuid> {
+    if let Some(user_id) = claims.uid {
+        return Ok(user_id);
+    }
+    if let Ok(user_id) = claims.sub.parse() {
+        return Ok(user_id);
+    }
+
+    sqlx::query_scalar(
+        "SELECT id FROM users \
+         WHERE email = $1 AND date_trunc('second', created_at) <= to_timestamp($2)",
+    )
+    .bind(&claims.sub)
+    .bind(claims.iat as f64)
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("User no longer exists".to_string()))
+}
+
+/// Rejects tokens whose user is gone, deactivated, or has invalidated their tokens,
+/// and refreshes the role from the database. Returns the user's id.
+pub async fn check_account(db: &PgPool, claims: &mut Claims) -> AppResult<Uuid> {
+    let user_uuid = resolve_subject(db, claims).await?;
+
+    let (is_active, role, token_version): (bool, String, i32) =
+        sqlx::query_as("SELECT is_active, role, token_version FROM users WHERE id = $1")
+            .bind(user_uuid)
+            .fetch_optional(db)
+            .await?
+            .ok_or_else(|| AppError::Unauthorized("User no longer exists".to_string()))?;
+
+    if claims.ver != token_version {
+        return Err(AppError::Unauthorized("Token has been revoked".to_string()));
+    }
+
+    if !is_active {
+        return Err(AppError::Forbidden("Account is deactivated".to_string()));
+    }
+
+    // Role changes apply without waiting for the token to expire
+    claims.role = role;
+
+    Ok(user_uuid)
+}
+
+async fn authenticate_api_key(state: &AppState, api_key: &str) -> AppResult<(Claims, Uuid)> {
+    // Keys are stored hashed, so look up by the hash of the presented value
+    let (user_id, email, role, is_active, scopes, expires_at): (
+        Uuid,
+        String,
+        String,
+        bool,
+        Vec<String>,
+        Option<DateTime<Utc>>,
+    ) = sqlx::query_as(
+        r#"
+        WITH key AS (
+            UPDATE api_keys
+            SET last_used_at = NOW()
+            WHERE key_hash = $1 AND (expires_at IS NULL OR expires_at > NOW())
+            RETURNING user_id, scopes, expires_at
+        )
+        SELECT u.id, u.email, u.role, u.is_active, key.scopes, key.expires_at
+        FROM key JOIN users u ON u.id = key.user_id
+        "#,
+    )
+    .bind(hash_token(api_key))
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::Unauthorized("Invalid or expired API key".to_string()))?;
+
+    if !is_active {
+        return Err(AppError::Forbidden("Account is deactivated".to_string()));
+    }
+
+    let claims = Claims {
+        sub: user_id.to_string(),
+        email,
+        role,
+        scopes,
+        uid: Some(user_id),
+        aud: None,
+        ver: 0,
+        cfp: None,
+        exp: expires_at.map_or(i64::MAX, |at| at.timestamp()),
+        iat: Utc::now().timestamp(),
+    };
+
+    Ok((claims, user_id))
+}
+
+/// Rejects requests whose authenticated user is not an admin.
+/// Must be layered inside `auth_middleware`.
+pub async fn require_admin(req: Request, next: Next) -> Result<Response, AppError> {
+    let claims = req
+        .extensions()
+        .get::<Claims>()
+        .ok_or_else(|| AppError::Unauthorized("Authentication required".to_string()))?;
+
+    if claims.role != ROLE_ADMIN {
+        return Err(AppError::Forbidden("Admin access required".to_string()));
+    }
 
     Ok(next.run(req).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use axum::{http::StatusCode, response::IntoResponse};
+    use std::time::Duration;
+
+    const MAX_LENGTH: usize = 64;
+
+    /// The 401 `authenticate_bearer` answers `authorization` with, as its message.
+    async fn rejection(authorization: Option<HeaderValue>) -> String {
+        let mut config = test_support::config();
+        config.auth_cookie_enabled = false;
+        config.bind_token_to_client = false;
+        config.jwt_max_length = MAX_LENGTH;
+        let db = test_support::stalled_pool(1, Duration::from_secs(1)).await;
+        let state = test_support::state(config, db).await;
+
+        let mut headers = HeaderMap::new();
+        if let Some(value) = authorization {
+            headers.insert(header::AUTHORIZATION, value);
+        }
+
+        let error = authenticate_bearer(&state, &headers)
+            .await
+            .expect_err("request must be rejected");
+        let message = error.to_string();
+        assert_eq!(error.into_response().status(), StatusCode::UNAUTHORIZED);
+        message
+    }
+
+    #[tokio::test]
+    async fn missing_header() {
+        let message = rejection(None).await;
+        assert_eq!(message, "Unauthorized: Missing authorization header");
+    }
+
+    #[tokio::test]
+    async fn header_that_is_not_visible_ascii() {
+        let value = HeaderValue::from_bytes(b"Bearer \xc3\xa9t\xff").unwrap();
+        let message = rejection(Some(value)).await;
+        assert_eq!(
+            message,
+            "Unauthorized: Authorization header contains invalid characters"
+        );
+    }
+
+    #[tokio::test]
+    async fn other_scheme() {
+        for value in ["Basic YWxpY2U6c2VjcmV0", "bearer abc", "Bearerabc", "abc"] {
+            let message = rejection(Some(HeaderValue::from_static(value))).await;
+            assert_eq!(
+                message,
+                "Unauthorized: Invalid authorization header format, expected 'Bearer <token>'",
+                "for {value:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn bearer_without_token() {
+        for value in ["Bearer", "Bearer ", "Bearer    "] {
+            let message = rejection(Some(HeaderValue::from_static(value))).await;
+            assert_eq!(
+                message, "Unauthorized: Authorization header carries no token",
+                "for {value:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn token_over_max_length() {
+        let value = format!("Bearer {}", "a".repeat(MAX_LENGTH + 1));
+        let message = rejection(Some(HeaderValue::from_str(&value).unwrap())).await;
+        assert_eq!(
+            message,
+            "Unauthorized: Token is longer than the 64 byte limit"
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_token() {
+        // Exactly at the limit is still read, and fails as a token
+        let value = format!("Bearer {}", "a".repeat(MAX_LENGTH));
+        let message = rejection(Some(HeaderValue::from_str(&value).unwrap())).await;
+        assert!(
+            message.starts_with("Authentication error: Invalid token:"),
+            "{message}"
+        );
+
+        let message = rejection(Some(HeaderValue::from_static("Bearer a.b.c"))).await;
+        assert!(
+            message.starts_with("Authentication error: Invalid token:"),
+            "{message}"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use crate::AppState;
+use axum::{
+    body::{to_bytes, Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+
+/// JSON keys whose values are never written to the logs.
+const REDACTED_FIELDS: &[&str] = &["password", "current_password", "new_password", "token"];
+
+/// Logs request and response bodies for a sampled fraction of requests.
+///
+/// Only bodies with a known length within `log_body_max_bytes` are buffered;
+/// anything larger or streamed passes through untouched.
+pub async fn body_logging_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let sample_rate = state.config.log_body_sample_rate;
+    if sample_rate <= 0.0 || rand::random::<f64>() >= sample_rate {
+        return next.run(req).await;
+    }
+
+    let max_bytes = state.config.log_body_max_bytes;
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+
+    let req = if body_fits(req.headers(), req.body().size_hint().exact(), max_bytes) {
+        let (parts, body) = req.into_parts();
+        let bytes = match to_bytes(body, max_bytes).await {
+            Ok(bytes) => bytes,
+            Err(_) => return (StatusCode::BAD_REQUEST, "Failed to read request body").into_response(),
+        };
+        tracing::info!(
+            %method,
+            %path,
+            body = %describe_body(&parts.headers, &bytes),
+            "Sampled request body"
+        );
+        Request::from_parts(parts, Body::from(bytes))
+    } else {
+        tracing::info!(%method, %path, "Sampled request body not logged (streamed or too large)");
+        req
+    };
+
+    let response = next.run(req).await;
+    let status = response.status();
+
+    if !body_fits(response.headers(), response.body().size_hint().exact(), max_bytes) {
+        tracing::info!(%method, %path, %status, "Sampled response body not logged (streamed or too large)");
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, max_bytes).await {
+        Ok(bytes) => bytes,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    tracing::info!(
+        %method,
+        %path,
+        %status,
+        body = %describe_body(&parts.headers, &bytes),
+        "Sampled response body"
+    );
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn body_fits(headers: &HeaderMap, exact_size: Option<u64>, max_bytes: usize) -> bool {
+    let length = exact_size.or_else(|| {
+        headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+    });
+
+    matches!(length, Some(len) if len <= max_bytes as u64)
+}
+
+fn describe_body(headers: &HeaderMap, bytes: &Bytes) -> String {
+    if bytes.is_empty() {
+        return "<empty>".to_string();
+    }
+
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"));
+
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut value) if is_json => {
+            redact(&mut value);
+            value.to_string()
+        }
+        _ => format!("<{} bytes, not logged>", bytes.len()),
+    }
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if REDACTED_FIELDS.contains(&key.as_str()) {
+                    *field = Value::String("[REDACTED]".to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
@@ -0,0 +1,47 @@
+use crate::AppState;
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+
+/// Sets `Cache-Control` on responses whose handler didn't: the directives
+/// configured in `CACHE_CONTROL_ROUTES` for successful `GET`/`HEAD` requests to
+/// that route, and `no-store` for everything else, mutations and errors included.
+pub async fn cache_control_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let read = matches!(*req.method(), Method::GET | Method::HEAD);
+    let directives = req
+        .extensions()
+        .get::<MatchedPath>()
+        .filter(|_| read)
+        .and_then(|matched| route_directives(&state, matched.as_str()));
+
+    let mut response = next.run(req).await;
+    if response.headers().contains_key(header::CACHE_CONTROL) {
+        return response;
+    }
+
+    let cacheable = response.status().is_success() || response.status().as_u16() == 304;
+    let value = directives
+        .filter(|_| cacheable)
+        .unwrap_or(HeaderValue::from_static("no-store"));
+    response.headers_mut().insert(header::CACHE_CONTROL, value);
+
+    response
+}
+
+/// The configured directives for `path`, which was checked to be a valid
+/// header value when the config was loaded.
+fn route_directives(state: &AppState, path: &str) -> Option<HeaderValue> {
+    state
+        .config
+        .cache_control_routes
+        .iter()
+        .find(|(route, _)| route == path)
+        .and_then(|(_, directives)| HeaderValue::from_str(directives).ok())
+}
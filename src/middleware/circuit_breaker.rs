@@ -0,0 +1,38 @@
+use crate::{error::AppError, AppState};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+/// Health checks report on the database themselves instead of being turned away.
+const BREAKER_EXEMPT_PREFIXES: &[&str] = &["/health"];
+
+/// Fails requests fast with `503` while the database circuit breaker is open.
+pub async fn database_breaker_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let path = req.uri().path();
+    if BREAKER_EXEMPT_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+    {
+        return Ok(next.run(req).await);
+    }
+
+    state
+        .db_breaker
+        .call(next.run(req))
+        .await
+        .map_err(|retry_after| {
+            // Round up so clients never retry a moment too early
+            let retry_after_secs =
+                retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            AppError::ServiceUnavailable(
+                "Database unavailable, retry shortly".to_string(),
+                Some(retry_after_secs),
+            )
+        })
+}
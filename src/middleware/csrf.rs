@@ -0,0 +1,49 @@
+use crate::{
+    error::AppError,
+    middleware::API_KEY_HEADER,
+    utils::cookie::{get_cookie, CSRF_COOKIE_NAME, CSRF_HEADER},
+    AppState,
+};
+use axum::{
+    extract::{Request, State},
+    http::{header, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Double-submit CSRF check for cookie-authenticated requests.
+///
+/// A state-changing request that authenticates with the auth cookie must echo
+/// the `csrf_token` cookie in `X-CSRF-Token`; another site can make the browser
+/// send the cookies but can't read them to set the header. Safe methods and
+/// requests authenticated by header (bearer token, API key) are not affected.
+/// Does nothing unless `AUTH_COOKIE_ENABLED` is on.
+pub async fn csrf_middleware(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let config = &state.config;
+    let headers = req.headers();
+
+    let exempt = !config.auth_cookie_enabled
+        || matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        || headers.contains_key(header::AUTHORIZATION)
+        || headers.contains_key(API_KEY_HEADER)
+        || get_cookie(headers, &config.auth_cookie_name).is_none_or(str::is_empty);
+    if exempt {
+        return next.run(req).await;
+    }
+
+    let cookie = get_cookie(headers, CSRF_COOKIE_NAME).unwrap_or_default();
+    let header = headers
+        .get(CSRF_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    if cookie.is_empty() || !constant_time_eq(cookie.as_bytes(), header.as_bytes()) {
+        return AppError::Forbidden("Missing or invalid CSRF token".to_string()).into_response();
+    }
+
+    next.run(req).await
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
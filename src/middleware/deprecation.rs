@@ -0,0 +1,97 @@
+use crate::utils::http::format_http_date;
+use axum::{
+    extract::Request,
+    http::{header, HeaderName, HeaderValue},
+    response::Response,
+};
+use chrono::NaiveDate;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+pub const DEPRECATION_HEADER: HeaderName = HeaderName::from_static("deprecation");
+pub const SUNSET_HEADER: HeaderName = HeaderName::from_static("sunset");
+
+/// Marks a route as deprecated: every response gets `Deprecation: true`, a `Sunset`
+/// date after which the route may be removed, and a `Warning` carrying `message`
+/// (say, what to use instead). Apply per method with `layer`.
+///
+/// `sunset` is a `YYYY-MM-DD` date (midnight UTC); anything else panics while the
+/// router is built, like other route definition mistakes.
+pub fn deprecated(sunset: &str, message: &str) -> DeprecationLayer {
+    let sunset = NaiveDate::parse_from_str(sunset, "%Y-%m-%d")
+        .unwrap_or_else(|_| panic!("sunset must be a YYYY-MM-DD date, got {sunset:?}"));
+    let sunset = format_http_date(sunset.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+
+    // 299 is "miscellaneous persistent warning"; `-` stands for this server
+    let message = message.replace('\\', "\\\\").replace('"', "\\\"");
+    let warning = format!(
+        "299 - \"Deprecated, removed after {}: {}\"",
+        sunset, message
+    );
+
+    DeprecationLayer {
+        headers: Arc::new([
+            (DEPRECATION_HEADER, HeaderValue::from_static("true")),
+            (SUNSET_HEADER, header_value(sunset)),
+            (header::WARNING, header_value(warning)),
+        ]),
+    }
+}
+
+fn header_value(value: String) -> HeaderValue {
+    HeaderValue::try_from(value).expect("deprecation message must be a valid header value")
+}
+
+#[derive(Debug, Clone)]
+pub struct DeprecationLayer {
+    headers: Arc<[(HeaderName, HeaderValue); 3]>,
+}
+
+impl<S> Layer<S> for DeprecationLayer {
+    type Service = Deprecation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Deprecation {
+            inner,
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Deprecation<S> {
+    inner: S,
+    headers: Arc<[(HeaderName, HeaderValue); 3]>,
+}
+
+impl<S> Service<Request> for Deprecation<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let headers = self.headers.clone();
+        let response = self.inner.call(req);
+
+        Box::pin(async move {
+            let mut response = response.await?;
+            for (name, value) in headers.iter() {
+                response.headers_mut().insert(name.clone(), value.clone());
+            }
+            Ok(response)
+        })
+    }
+}
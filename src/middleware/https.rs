@@ -0,0 +1,47 @@
+use crate::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Health checks come from the load balancer itself, usually over plain HTTP.
+const FORCE_HTTPS_EXEMPT_PREFIXES: &[&str] = &["/health"];
+
+/// With `FORCE_HTTPS`, redirects requests the proxy received over plain HTTP
+/// (`X-Forwarded-Proto: http`) to the same path on `https://PUBLIC_HOST`. Requests
+/// without the header are left alone, since only a proxy can tell how the client
+/// connected. `308` keeps the method and body of non-GET requests.
+pub async fn force_https_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let path = req.uri().path();
+    let exempt = FORCE_HTTPS_EXEMPT_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix));
+
+    let forwarded_http = req
+        .headers()
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("http"));
+
+    match state.config.public_host.as_deref() {
+        Some(host) if state.config.force_https && forwarded_http && !exempt => {
+            let path_and_query = req
+                .uri()
+                .path_and_query()
+                .map_or("/", |path_and_query| path_and_query.as_str());
+            let location = format!("https://{}{}", host, path_and_query);
+            (
+                StatusCode::PERMANENT_REDIRECT,
+                [(header::LOCATION, location)],
+            )
+                .into_response()
+        }
+        _ => next.run(req).await,
+    }
+}
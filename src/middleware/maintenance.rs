@@ -0,0 +1,31 @@
+use crate::{error::AppError, AppState};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::atomic::Ordering;
+
+/// Paths that keep working while maintenance mode is on: health checks so
+/// orchestrators don't restart the pod, and the toggle itself.
+const MAINTENANCE_EXEMPT_PREFIXES: &[&str] = &["/health", "/admin/maintenance"];
+
+pub async fn maintenance_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let path = req.uri().path();
+    let exempt = MAINTENANCE_EXEMPT_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix));
+
+    if exempt || !state.maintenance.load(Ordering::Relaxed) {
+        return Ok(next.run(req).await);
+    }
+
+    Err(AppError::ServiceUnavailable(
+        "Service is under maintenance".to_string(),
+        state.config.maintenance_retry_after,
+    ))
+}
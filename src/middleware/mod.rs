@@ -1,3 +1,29 @@
 pub mod auth;
+pub mod body_logging;
+pub mod cache_control;
+pub mod circuit_breaker;
+pub mod csrf;
+pub mod deprecation;
+pub mod https;
+pub mod maintenance;
+pub mod query_count;
+pub mod rate_limit;
+pub mod scope;
+pub mod security_headers;
+pub mod timestamps;
+pub mod trace;
 
 pub use auth::*;
+pub use body_logging::*;
+pub use cache_control::*;
+pub use circuit_breaker::*;
+pub use csrf::*;
+pub use deprecation::*;
+pub use https::*;
+pub use maintenance::*;
+pub use query_count::*;
+pub use rate_limit::*;
+pub use scope::*;
+pub use security_headers::*;
+pub use timestamps::*;
+pub use trace::*;
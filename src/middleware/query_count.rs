@@ -0,0 +1,36 @@
+use crate::{query_count::count_queries, AppState};
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+
+/// Response header with the number of database statements the request ran.
+pub const QUERY_COUNT_HEADER: &str = "x-query-count";
+
+/// With `QUERY_COUNT_ENABLED`, counts the database statements each request runs
+/// so N+1 query patterns stand out: logged at debug level, and outside
+/// production also returned in `X-Query-Count`.
+pub async fn query_count_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !state.config.query_count_enabled {
+        return next.run(req).await;
+    }
+
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let (mut response, queries) = count_queries(next.run(req)).await;
+
+    tracing::debug!(%method, %path, queries, "Database queries for request");
+    if !state.config.is_production() {
+        response
+            .headers_mut()
+            .insert(QUERY_COUNT_HEADER, HeaderValue::from(queries));
+    }
+
+    response
+}
@@ -0,0 +1,36 @@
+use crate::{error::AppError, extract::AuthUser, AppState};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Duration;
+
+/// Limits each authenticated user to `USER_RATE_LIMIT` requests per
+/// `USER_RATE_LIMIT_WINDOW`, whatever address they come from. Must run after
+/// `auth_middleware`, which provides the user id.
+pub async fn user_rate_limit_middleware(
+    State(state): State<AppState>,
+    AuthUser(user_uuid): AuthUser,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let Err(retry_after) = state.user_rate_limiter.check(user_uuid).await {
+        return Err(rate_limited(retry_after));
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// The `429` for a request over a limit whose window resets in `retry_after`.
+pub fn rate_limited(retry_after: Duration) -> AppError {
+    // Round up so clients never retry a moment too early
+    let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    AppError::TooManyRequests(
+        format!(
+            "Rate limit exceeded. Try again in {} seconds.",
+            retry_after_secs
+        ),
+        Some(retry_after_secs),
+    )
+}
@@ -0,0 +1,64 @@
+use crate::{error::AppError, utils::auth::Claims};
+use axum::{extract::Request, response::IntoResponse, response::Response};
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Requires the authenticated token or API key to carry `scope`, answering 403 otherwise.
+/// Apply per method with `route_layer`, inside `auth_middleware`.
+pub fn require_scope(scope: &'static str) -> RequireScopeLayer {
+    RequireScopeLayer { scope }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RequireScopeLayer {
+    scope: &'static str,
+}
+
+impl<S> Layer<S> for RequireScopeLayer {
+    type Service = RequireScope<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequireScope {
+            inner,
+            scope: self.scope,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequireScope<S> {
+    inner: S,
+    scope: &'static str,
+}
+
+impl<S> Service<Request> for RequireScope<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let allowed = req
+            .extensions()
+            .get::<Claims>()
+            .is_some_and(|claims| claims.has_scope(self.scope));
+
+        if !allowed {
+            let error = AppError::Forbidden(format!("Missing required scope: {}", self.scope));
+            return Box::pin(async move { Ok(error.into_response()) });
+        }
+
+        Box::pin(self.inner.call(req))
+    }
+}
@@ -0,0 +1,103 @@
+use crate::config::Config;
+use axum::{
+    extract::Request,
+    http::{header, HeaderName, HeaderValue},
+    response::Response,
+};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tower::{Layer, Service};
+
+/// Adds the security headers enabled in `config` to every response. Headers a
+/// handler already set are left alone.
+pub fn security_headers(config: &Config) -> SecurityHeadersLayer {
+    let mut headers = Vec::new();
+
+    // HSTS over plain HTTP is ignored by browsers, and in development it would
+    // pin localhost to HTTPS
+    if config.is_production() && config.tls_enabled && config.hsts_max_age > 0 {
+        headers.push((
+            header::STRICT_TRANSPORT_SECURITY,
+            HeaderValue::from_str(&format!(
+                "max-age={}; includeSubDomains",
+                config.hsts_max_age
+            ))
+            .expect("numeric HSTS header is valid"),
+        ));
+    }
+    if config.nosniff_header {
+        headers.push((
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ));
+    }
+    if config.frame_deny_header {
+        headers.push((header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY")));
+    }
+    if let Some(policy) = &config.content_security_policy {
+        // Checked by `Config::from_env`
+        if let Ok(value) = HeaderValue::from_str(policy) {
+            headers.push((header::CONTENT_SECURITY_POLICY, value));
+        }
+    }
+
+    SecurityHeadersLayer {
+        headers: headers.into(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersLayer {
+    headers: Arc<[(HeaderName, HeaderValue)]>,
+}
+
+impl<S> Layer<S> for SecurityHeadersLayer {
+    type Service = SecurityHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SecurityHeaders {
+            inner,
+            headers: self.headers.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders<S> {
+    inner: S,
+    headers: Arc<[(HeaderName, HeaderValue)]>,
+}
+
+impl<S> Service<Request> for SecurityHeaders<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let headers = self.headers.clone();
+        let future = self.inner.call(req);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            for (name, value) in headers.iter() {
+                response
+                    .headers_mut()
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            Ok(response)
+        })
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{
+    config::TimestampFormat, error::AppError, utils::timestamp::with_timestamp_format, AppState,
+};
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+
+/// Response header naming the timestamp representation used in the body.
+pub const TIMESTAMP_FORMAT_HEADER: &str = "x-timestamp-format";
+
+/// Picks how `created_at`/`updated_at` are written for this request: the
+/// `?timestamps=` query parameter if given, else `TIMESTAMP_FORMAT`.
+pub async fn timestamp_format_middleware(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let requested = form_urlencoded::parse(req.uri().query().unwrap_or_default().as_bytes())
+        .find(|(name, _)| name == "timestamps")
+        .map(|(_, value)| value.into_owned());
+    let format = match requested {
+        Some(value) => TimestampFormat::parse(&value).ok_or_else(|| {
+            AppError::Validation(format!(
+                "timestamps must be rfc3339 or epoch_millis (got '{}')",
+                value
+            ))
+        })?,
+        None => state.config.timestamp_format,
+    };
+
+    let mut response = with_timestamp_format(format, next.run(req)).await;
+    response.headers_mut().insert(
+        TIMESTAMP_FORMAT_HEADER,
+        HeaderValue::from_static(format.as_str()),
+    );
+
+    Ok(response)
+}
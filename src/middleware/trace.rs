@@ -0,0 +1,60 @@
+use crate::config::Config;
+use axum::{body::Body, extract::Request, response::Response};
+use std::{sync::Arc, time::Duration};
+use tower_http::{
+    classify::{ServerErrorsAsFailures, ServerErrorsFailureClass, SharedClassifier},
+    trace::{
+        DefaultMakeSpan, DefaultOnBodyChunk, DefaultOnEos, DefaultOnFailure, DefaultOnRequest,
+        DefaultOnResponse, MakeSpan, OnFailure, OnRequest, OnResponse, TraceLayer,
+    },
+};
+use tracing::Span;
+
+/// Request tracing that skips `LOG_EXCLUDE_PATHS`. Excluded requests get no span,
+/// and the request, response and failure events check for that before logging.
+#[allow(clippy::type_complexity)]
+pub fn request_trace(
+    config: &Config,
+) -> TraceLayer<
+    SharedClassifier<ServerErrorsAsFailures>,
+    impl MakeSpan<Body> + Clone,
+    impl OnRequest<Body> + Clone,
+    impl OnResponse<Body> + Clone,
+    DefaultOnBodyChunk,
+    DefaultOnEos,
+    impl OnFailure<ServerErrorsFailureClass> + Clone,
+> {
+    let excluded: Arc<[String]> = config.log_exclude_paths.clone().into();
+
+    TraceLayer::new_for_http()
+        .make_span_with(move |request: &Request| {
+            if excluded.iter().any(|path| path == request.uri().path()) {
+                Span::none()
+            } else {
+                DefaultMakeSpan::new().make_span(request)
+            }
+        })
+        .on_request(|request: &Request, span: &Span| {
+            if traced(span) {
+                DefaultOnRequest::new().on_request(request, span);
+            }
+        })
+        .on_response(|response: &Response, latency: Duration, span: &Span| {
+            if traced(span) {
+                DefaultOnResponse::new().on_response(response, latency, span);
+            }
+        })
+        .on_failure(
+            |failure: ServerErrorsFailureClass, latency: Duration, span: &Span| {
+                if traced(span) {
+                    DefaultOnFailure::new().on_failure(failure, latency, span);
+                }
+            },
+        )
+}
+
+/// `Span::none()` carries no metadata, unlike a real span that the log filter
+/// happens to disable.
+fn traced(span: &Span) -> bool {
+    span.metadata().is_some()
+}
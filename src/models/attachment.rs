@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::utils::timestamp;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    #[allow(dead_code)]
+    pub user_id: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttachmentResponse {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    /// Presigned link straight to the storage backend, when it supports one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Attachment> for AttachmentResponse {
+    fn from(attachment: Attachment) -> Self {
+        Self {
+            id: attachment.id,
+            item_id: attachment.item_id,
+            filename: attachment.filename,
+            content_type: attachment.content_type,
+            size_bytes: attachment.size_bytes,
+            download_url: None,
+            created_at: attachment.created_at,
+        }
+    }
+}
+
+/// Longest filename kept from an upload; longer names are truncated.
+pub const MAX_ATTACHMENT_FILENAME_LEN: usize = 255;
+
+/// Reduces a client-supplied filename to its last path component, without
+/// control characters, falling back to `attachment` when nothing is left.
+pub fn sanitize_attachment_filename(name: Option<&str>) -> String {
+    let base = name
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_ATTACHMENT_FILENAME_LEN)
+        .collect();
+    let cleaned = cleaned.trim();
+
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        "attachment".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
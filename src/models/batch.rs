@@ -0,0 +1,52 @@
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+/// Largest number of elements accepted by a single batch request.
+pub const MAX_BATCH_SIZE: u64 = 100;
+
+/// How a batch request treats failing elements.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatchMode {
+    /// All elements succeed or none are applied.
+    #[default]
+    Atomic,
+    /// Each element is applied independently; failures are reported per element.
+    Partial,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchQuery {
+    #[serde(default)]
+    pub mode: BatchMode,
+}
+
+/// Outcome for one element of a partial batch, reported in a `207 Multi-Status` body.
+#[derive(Debug, Serialize)]
+pub struct BatchItemResult<T> {
+    pub index: usize,
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl<T> BatchItemResult<T> {
+    /// Reports `error` with the status and message it would have as a whole response.
+    pub fn failed(index: usize, id: Option<Uuid>, error: AppError) -> Self {
+        let message = error.to_string();
+        Self {
+            index,
+            status: error.into_response().status().as_u16(),
+            id,
+            item: None,
+            error: Some(message),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::timestamp;
+
+pub const MAX_COMMENT_LEN: u64 = 5000;
+
+/// `?sort=` values accepted by `GET /items/:id/comments`, and the columns they order by.
+pub const COMMENT_SORT_FIELDS: &[(&str, &str)] = &[("created_at", "created_at")];
+
+/// Oldest first, so a thread reads top to bottom.
+pub const DEFAULT_COMMENT_SORT: &str = "created_at";
+
+/// A comment with its author's username, as loaded by the comment queries.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Comment {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub body: String,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateComment {
+    #[validate(length(
+        min = 1,
+        max = "MAX_COMMENT_LEN",
+        message = "Comment must be between 1 and 5000 characters"
+    ))]
+    pub body: String,
+}
@@ -0,0 +1,40 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::{
+    models::{
+        ApiKeyResponse, AttachmentResponse, Comment, ItemResponse, UserPreferences, UserResponse,
+        WorkspaceResponse,
+    },
+    utils::timestamp,
+};
+
+/// The account part of `GET /auth/me/export`. Like every other part of the
+/// export, it leaves out password and key hashes and token versions.
+#[derive(Debug, Serialize)]
+pub struct ExportedUser {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    pub preferences: UserPreferences,
+}
+
+/// An item the user created, with everything attached to it.
+#[derive(Debug, Serialize)]
+pub struct ExportedItem {
+    #[serde(flatten)]
+    pub item: ItemResponse,
+    pub comments: Vec<Comment>,
+    /// File metadata only; the files themselves are downloaded separately.
+    pub attachments: Vec<AttachmentResponse>,
+}
+
+/// The start of `GET /auth/me/export`, small enough to build in one go. `items`
+/// and `comments` are streamed after it.
+#[derive(Debug, Serialize)]
+pub struct DataExport {
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub exported_at: DateTime<Utc>,
+    pub user: ExportedUser,
+    pub workspaces: Vec<WorkspaceResponse>,
+    pub api_keys: Vec<ApiKeyResponse>,
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+use crate::utils::timestamp;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Invite {
+    pub id: Uuid,
+    pub email: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateInvite {
+    /// Restricts the invite to this address when set.
+    #[validate(email(message = "Invalid email format"))]
+    pub email: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Returned only once, when the invite is minted; the token is stored hashed.
+#[derive(Debug, Serialize)]
+pub struct InviteResponse {
+    pub id: Uuid,
+    pub token: String,
+    pub email: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub created_at: DateTime<Utc>,
+}
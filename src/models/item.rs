@@ -1,25 +1,71 @@
-use serde::{Deserialize, Serialize};
+use serde::{
+    ser::{Error as _, SerializeMap},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use sqlx::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use validator::Validate;
 
+use crate::{
+    error::AppError,
+    models::{validate_tags, MAX_BATCH_SIZE},
+    utils::timestamp,
+};
+
+/// Every status the transition rules know about, and so the values
+/// `DEFAULT_ITEM_STATUS` may take.
+pub const ITEM_STATUSES: &[&str] = &["active", "todo", "in_progress", "done"];
+
+/// Trims and lowercases a status, the form statuses are stored and filtered in,
+/// so `?status=DONE` finds `done` items.
+pub fn normalize_status(status: &str) -> String {
+    status.trim().to_lowercase()
+}
+
+/// Statuses an item may move to from `from` when transition rules are enforced.
+/// `active` is the legacy column default; unknown statuses have no way forward.
+pub fn allowed_status_transitions(from: &str) -> &'static [&'static str] {
+    match from {
+        "active" => &["todo", "in_progress", "done"],
+        "todo" => &["in_progress", "done"],
+        "in_progress" => &["todo", "done"],
+        "done" => &["in_progress"],
+        _ => &[],
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Item {
     pub id: Uuid,
     pub user_id: Uuid,
+    pub workspace_id: Uuid,
     pub title: String,
     pub description: Option<String>,
     pub status: String,
+    pub external_id: Option<String>,
+    pub tags: Vec<String>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub archived_at: Option<DateTime<Utc>>,
+    pub position: i64,
+    pub completed_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateItem {
     #[validate(length(min = 1, max = 255, message = "Title must be between 1 and 255 characters"))]
     pub title: String,
     pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Vec<String>,
+    /// Defaults to the creator's personal workspace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -28,17 +74,234 @@ pub struct UpdateItem {
     pub title: Option<String>,
     pub description: Option<String>,
     pub status: Option<String>,
+    /// Replaces all of the item's tags.
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Option<Vec<String>>,
+    /// Omitted keeps the due date, `null` clears it.
+    #[serde(default, deserialize_with = "present")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+}
+
+/// Tells a field sent as `null` (`Some(None)`) apart from one left out (`None`).
+pub(crate) fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::deserialize(deserializer).map(Some)
+}
+
+/// `?sort=` values accepted by `GET /items`, and the columns they order by.
+pub const ITEM_SORT_FIELDS: &[(&str, &str)] = &[
+    ("created_at", "created_at"),
+    ("updated_at", "updated_at"),
+    ("title", "title"),
+    ("status", "status"),
+    ("due_at", "due_at"),
+    ("position", "position"),
+    ("completed_at", "completed_at"),
+];
+
+pub const DEFAULT_ITEM_SORT: &str = "-created_at";
+
+pub const MAX_ITEM_SEARCH_LEN: u64 = 200;
+
+/// How long deleted items are remembered for delta syncs. Clients whose last
+/// sync is older have to fetch the full list again.
+pub const ITEM_TOMBSTONE_RETENTION_DAYS: i64 = 90;
+
+/// `GET /items` filters beyond the shared list parameters.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ItemListFilter {
+    /// Narrows the list to one of the user's workspaces.
+    pub workspace_id: Option<Uuid>,
+    /// Search terms, matched against title and description and ranked by relevance.
+    #[validate(length(min = 1, max = "MAX_ITEM_SEARCH_LEN", message = "q must be between 1 and 200 characters"))]
+    pub q: Option<String>,
+    /// Only items carrying this tag.
+    pub tag: Option<String>,
+    /// Only items changed after this RFC 3339 timestamp, plus tombstones of
+    /// items deleted since then.
+    pub since: Option<DateTime<Utc>>,
+    /// `true` for items past their due date and not yet done, `false` for the rest.
+    pub overdue: Option<bool>,
+    /// Only items due before this RFC 3339 timestamp.
+    pub due_before: Option<DateTime<Utc>>,
+    /// Only items due after this RFC 3339 timestamp.
+    pub due_after: Option<DateTime<Utc>>,
+    /// Only items that became `done` after this RFC 3339 timestamp.
+    pub completed_after: Option<DateTime<Utc>>,
+    /// `true` lists archived items instead of the ones in use.
+    #[serde(default)]
+    pub archived: bool,
+    /// Comma-separated response fields, e.g. `id,title,status`.
+    pub fields: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct BatchCreateItems {
+    #[validate(length(min = 1, max = "MAX_BATCH_SIZE", message = "Between 1 and 100 items per batch"))]
+    #[validate(nested)]
+    pub items: Vec<CreateItem>,
+}
+
+/// Most items one `POST /items/reorder` may move.
+pub const MAX_REORDER_SIZE: u64 = 1000;
+
+/// Body of `POST /items/reorder`: ids in their new order.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ReorderItems {
+    #[validate(length(min = 1, max = "MAX_REORDER_SIZE", message = "Between 1 and 1000 ids per reorder"))]
+    pub ids: Vec<Uuid>,
+}
+
+/// Body of `POST /items/batch-get`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct BatchGetItems {
+    #[validate(length(min = 1, max = "MAX_BATCH_SIZE", message = "Between 1 and 100 ids per batch"))]
+    pub ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct BatchDeleteItems {
+    #[validate(length(min = 1, max = "MAX_BATCH_SIZE", message = "Between 1 and 100 ids per batch"))]
+    pub ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct BulkStatusUpdate {
+    #[validate(length(min = 1, max = "MAX_BATCH_SIZE", message = "Between 1 and 100 ids per batch"))]
+    pub ids: Vec<Uuid>,
+    #[validate(length(min = 1, max = 50, message = "Status must be between 1 and 50 characters"))]
+    pub status: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkStatusResponse {
+    pub updated: u64,
+}
+
+/// Statuses from which an item may move to `to` when transition rules are enforced.
+pub fn statuses_allowed_into(to: &str) -> Vec<&'static str> {
+    ITEM_STATUSES
+        .iter()
+        .copied()
+        .filter(|from| allowed_status_transitions(from).contains(&to))
+        .collect()
+}
+
+/// Body of `PUT /items/by-external-id/:ext`; `status`, `tags` and `due_at` are kept on
+/// update when omitted.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpsertItem {
+    #[validate(length(min = 1, max = 255, message = "Title must be between 1 and 255 characters"))]
+    pub title: String,
+    pub description: Option<String>,
+    pub status: Option<String>,
+    #[validate(custom(function = "validate_tags"))]
+    pub tags: Option<Vec<String>>,
+    pub due_at: Option<DateTime<Utc>>,
+}
+
+pub const MAX_EXTERNAL_ID_LEN: usize = 255;
+
+/// External ids are opaque keys from other systems, restricted to URL-safe characters.
+pub fn is_valid_external_id(external_id: &str) -> bool {
+    !external_id.is_empty()
+        && external_id.len() <= MAX_EXTERNAL_ID_LEN
+        && external_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+/// Extra representations `GET /items/:id` can add with `?render=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemRender {
+    /// The description, treated as Markdown, as sanitized HTML.
+    Html,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemReadQuery {
+    pub render: Option<ItemRender>,
+    /// Comma-separated response fields, e.g. `id,title,status`.
+    pub fields: Option<String>,
+}
+
+/// Item fields a client may ask for with `?fields=`.
+pub const SELECTABLE_ITEM_FIELDS: &[&str] = &[
+    "id", "user_id", "workspace_id", "title", "description", "status", "external_id", "tags",
+    "due_at", "archived_at", "position", "completed_at", "created_at", "updated_at",
+    "description_html", "rank",
+];
+
+/// The fields a response is limited to, or `None` for all of them. `id` is always kept
+/// so projected items can still be told apart.
+pub fn parse_item_fields(fields: Option<&str>) -> Result<Option<Vec<&'static str>>, AppError> {
+    let Some(fields) = fields else {
+        return Ok(None);
+    };
+
+    let mut selected = vec!["id"];
+    for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        let known = SELECTABLE_ITEM_FIELDS
+            .iter()
+            .find(|known| **known == field)
+            .ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Unknown field '{}' in fields. Allowed fields: {}",
+                    field,
+                    SELECTABLE_ITEM_FIELDS.join(", ")
+                ))
+            })?;
+        if !selected.contains(known) {
+            selected.push(known);
+        }
+    }
+
+    Ok(Some(selected))
+}
+
+/// Item fields a client may name in an update mask.
+pub const UPDATABLE_ITEM_FIELDS: &[&str] = &["title", "description", "status", "tags", "due_at"];
+
+#[derive(Debug, Deserialize)]
+pub struct FieldMaskQuery {
+    /// Comma-separated list of fields to write, e.g. `title,status`.
+    pub fields: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ItemResponse {
     pub id: Uuid,
     pub user_id: Uuid,
+    pub workspace_id: Uuid,
     pub title: String,
     pub description: Option<String>,
     pub status: String,
+    pub external_id: Option<String>,
+    pub tags: Vec<String>,
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    pub due_at: Option<DateTime<Utc>>,
+    /// When the item was archived; `null` while it is in use.
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    pub archived_at: Option<DateTime<Utc>>,
+    /// Place in the manual order set with `POST /items/reorder`.
+    pub position: i64,
+    /// When the item last became `done`; `null` while it isn't.
+    #[serde(serialize_with = "timestamp::serialize_option")]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(serialize_with = "timestamp::serialize")]
     pub created_at: DateTime<Utc>,
+    #[serde(serialize_with = "timestamp::serialize")]
     pub updated_at: DateTime<Utc>,
+    /// `description` rendered to sanitized HTML, with `?render=html`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_html: Option<String>,
+    /// Relevance to the `?q=` search, for debugging result order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f32>,
 }
 
 impl From<Item> for ItemResponse {
@@ -46,11 +309,78 @@ impl From<Item> for ItemResponse {
         Self {
             id: item.id,
             user_id: item.user_id,
+            workspace_id: item.workspace_id,
             title: item.title,
             description: item.description,
             status: item.status,
+            external_id: item.external_id,
+            tags: item.tags,
+            due_at: item.due_at,
+            archived_at: item.archived_at,
+            position: item.position,
+            completed_at: item.completed_at,
             created_at: item.created_at,
             updated_at: item.updated_at,
+            description_html: None,
+            rank: None,
         }
     }
 }
+
+/// An item deleted since the client's last sync.
+#[derive(Debug, Serialize, FromRow)]
+pub struct ItemTombstone {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// An item limited to the fields picked by `?fields=`.
+#[derive(Debug)]
+pub struct ProjectedItem {
+    pub item: ItemResponse,
+    pub fields: Option<std::sync::Arc<[&'static str]>>,
+}
+
+impl Serialize for ProjectedItem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Some(fields) = &self.fields else {
+            return self.item.serialize(serializer);
+        };
+
+        // Going through a JSON value keeps each field's own serializer, timestamps
+        // included; the allowlist's order matches the struct's
+        let serde_json::Value::Object(values) =
+            serde_json::to_value(&self.item).map_err(S::Error::custom)?
+        else {
+            return Err(S::Error::custom("item did not serialize to a map"));
+        };
+        // Requested fields may be absent (`rank` outside a search), and formats like
+        // MessagePack write the length up front, so only present entries are counted
+        let entries: Vec<_> = SELECTABLE_ITEM_FIELDS
+            .iter()
+            .filter(|f| fields.contains(f))
+            .filter_map(|field| values.get(*field).map(|value| (field, value)))
+            .collect();
+        let mut map = serializer.serialize_map(Some(entries.len()))?;
+        for (field, value) in entries {
+            map.serialize_entry(field, value)?;
+        }
+        map.end()
+    }
+}
+
+/// Response of `GET /items?since=`. `server_time` is the `since` to send next time.
+#[derive(Debug, Serialize)]
+pub struct ItemChanges {
+    pub items: Vec<ProjectedItem>,
+    pub deleted: Vec<ItemTombstone>,
+    pub server_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PatchItemResponse {
+    #[serde(flatten)]
+    pub item: ItemResponse,
+    pub changed_fields: Vec<&'static str>,
+}
@@ -1,5 +1,23 @@
 pub mod user;
 pub mod item;
+pub mod api_key;
+pub mod invite;
+pub mod batch;
+pub mod pagination;
+pub mod attachment;
+pub mod workspace;
+pub mod comment;
+pub mod tag;
+pub mod export;
 
 pub use user::*;
 pub use item::*;
+pub use api_key::*;
+pub use invite::*;
+pub use batch::*;
+pub use pagination::*;
+pub use attachment::*;
+pub use workspace::*;
+pub use comment::*;
+pub use tag::*;
+pub use export::*;
@@ -0,0 +1,133 @@
+use axum::http::Uri;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::error::{AppError, FieldError};
+
+pub const DEFAULT_PER_PAGE: i64 = 20;
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// Query parameters shared by list endpoints: `?page=2&per_page=50&sort=-created_at&status=done`.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ListParams {
+    #[serde(default = "default_page")]
+    #[validate(range(min = 1, message = "page must be at least 1"))]
+    pub page: i64,
+    #[serde(default = "default_per_page")]
+    #[validate(range(
+        min = 1,
+        max = "MAX_PER_PAGE",
+        message = "per_page must be between 1 and 100"
+    ))]
+    pub per_page: i64,
+    /// A field from the endpoint's sort allowlist, prefixed with `-` for descending order.
+    pub sort: Option<String>,
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "status must be between 1 and 50 characters"
+    ))]
+    pub status: Option<String>,
+}
+
+fn default_page() -> i64 {
+    1
+}
+
+fn default_per_page() -> i64 {
+    DEFAULT_PER_PAGE
+}
+
+impl ListParams {
+    pub fn offset(&self) -> i64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+
+    /// Resolves `sort` (or `default` when absent) against `allowed` (`(param name, SQL column)` pairs) into an
+    /// `ORDER BY` expression. Only allowlisted columns ever reach the SQL.
+    pub fn order_by(&self, allowed: &[(&str, &str)], default: &str) -> Result<String, AppError> {
+        let sort = self.sort.as_deref().unwrap_or(default);
+        let (name, direction) = match sort.strip_prefix('-') {
+            Some(name) => (name, "DESC"),
+            None => (sort, "ASC"),
+        };
+
+        let column = allowed
+            .iter()
+            .find(|(param, _)| *param == name)
+            .map(|(_, column)| *column)
+            .ok_or_else(|| {
+                let names: Vec<&str> = allowed.iter().map(|(param, _)| *param).collect();
+                AppError::InvalidFields(vec![FieldError {
+                    field: "sort".to_string(),
+                    pointer: "/sort".to_string(),
+                    code: "invalid_sort".to_string(),
+                    message: format!(
+                        "Cannot sort by '{}'. Allowed: {} (prefix with '-' for descending)",
+                        name,
+                        names.join(", ")
+                    ),
+                }])
+            })?;
+
+        Ok(format!("{} {}", column, direction))
+    }
+}
+
+/// Whether `sort` names an allowlisted field, optionally prefixed with `-`.
+pub fn is_allowed_sort(sort: &str, allowed: &[(&str, &str)]) -> bool {
+    let name = sort.strip_prefix('-').unwrap_or(sort);
+    allowed.iter().any(|(param, _)| *param == name)
+}
+
+/// One page of a list endpoint's results.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+    pub links: PageLinks,
+}
+
+/// URLs of the current page and its neighbours, keeping the request's other query
+/// parameters. `next` and `prev` are left out at either end of the results.
+#[derive(Debug, Serialize)]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub self_link: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prev: Option<String>,
+}
+
+impl PageLinks {
+    /// Links relative to the server root, built from the path the request arrived on
+    /// so any prefix the router is mounted under is kept.
+    pub fn new(uri: &Uri, params: &ListParams, total: i64) -> Self {
+        // An empty result still has a (first) page
+        let last_page = ((total + params.per_page - 1) / params.per_page).max(1);
+        let link = |page: i64| page_url(uri, page, params.per_page);
+
+        Self {
+            self_link: link(params.page),
+            next: (params.page < last_page).then(|| link(params.page + 1)),
+            // Past the end, `prev` leads back to the last page that has results
+            prev: (params.page > 1).then(|| link((params.page - 1).min(last_page))),
+        }
+    }
+}
+
+fn page_url(uri: &Uri, page: i64, per_page: i64) -> String {
+    let mut query = form_urlencoded::Serializer::new(String::new());
+    for (name, value) in form_urlencoded::parse(uri.query().unwrap_or_default().as_bytes()) {
+        if name != "page" && name != "per_page" {
+            query.append_pair(&name, &value);
+        }
+    }
+    query.append_pair("page", &page.to_string());
+    query.append_pair("per_page", &per_page.to_string());
+
+    format!("{}?{}", uri.path(), query.finish())
+}
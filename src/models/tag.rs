@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::{Validate, ValidationError};
+
+pub const MAX_TAG_LEN: usize = 50;
+
+/// `?sort=` values accepted by `GET /tags`, and the columns they order by.
+pub const TAG_SORT_FIELDS: &[(&str, &str)] = &[("count", "count"), ("tag", "tag")];
+
+/// Most used first, for tag clouds.
+pub const DEFAULT_TAG_SORT: &str = "-count";
+
+/// A tag and the number of the user's items carrying it.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+/// Lowercases a tag and collapses its whitespace, so "Work  Stuff " and
+/// "work stuff" are the same tag.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Body of `POST /tags/rename`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct RenameTag {
+    #[validate(custom(function = "validate_tag"))]
+    pub from: String,
+    #[validate(custom(function = "validate_tag"))]
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenameTagResponse {
+    pub updated: u64,
+}
+
+pub fn validate_tag(tag: &str) -> Result<(), ValidationError> {
+    let len = normalize_tag(tag).chars().count();
+    if (1..=MAX_TAG_LEN).contains(&len) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_tag")
+            .with_message("Tags must be between 1 and 50 characters".into()))
+    }
+}
+
+pub fn validate_tags(tags: &[String]) -> Result<(), ValidationError> {
+    tags.iter().try_for_each(|tag| validate_tag(tag))
+}
+
+/// Normalizes tags and drops repeats, keeping the first occurrence's position.
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = normalize_tag(&tag);
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
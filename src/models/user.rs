@@ -1,16 +1,36 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use super::item::present;
+use crate::utils::{auth::Claims, timestamp};
+
+pub const ROLE_USER: &str = "user";
+pub const ROLE_ADMIN: &str = "admin";
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: Uuid,
     pub email: String,
     pub username: String,
-    #[serde(skip_serializing)]
+    /// Never serialized, so users read back from the cache have an empty hash.
+    #[serde(skip_serializing, default)]
     pub password_hash: String,
+    pub is_active: bool,
+    /// Set when an admin deactivated the account; only an admin can clear it.
+    #[serde(default)]
+    pub locked_at: Option<DateTime<Utc>>,
+    pub role: String,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub last_login_ip: Option<String>,
+    pub token_version: i32,
+    pub preferences: sqlx::types::Json<UserPreferences>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -23,6 +43,8 @@ pub struct CreateUser {
     pub username: String,
     #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
     pub password: String,
+    /// Required when signup is invite-only.
+    pub invite_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -38,6 +60,15 @@ pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
     pub username: String,
+    pub is_active: bool,
+    pub locked_at: Option<DateTime<Utc>>,
+    pub role: String,
+    pub last_login_at: Option<DateTime<Utc>>,
+    pub last_login_ip: Option<String>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub bio: Option<String>,
+    #[serde(serialize_with = "timestamp::serialize")]
     pub created_at: DateTime<Utc>,
 }
 
@@ -47,13 +78,176 @@ impl From<User> for UserResponse {
             id: user.id,
             email: user.email,
             username: user.username,
+            is_active: user.is_active,
+            locked_at: user.locked_at,
+            role: user.role,
+            last_login_at: user.last_login_at,
+            last_login_ip: user.last_login_ip,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+            bio: user.bio,
             created_at: user.created_at,
         }
     }
 }
 
+/// Body of `PATCH /auth/me`. Omitted fields are kept; `null` clears them.
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfile {
+    #[serde(default, deserialize_with = "present")]
+    #[validate(length(max = 100, message = "Display name must be at most 100 characters"))]
+    pub display_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    #[validate(custom(function = "validate_avatar_url"))]
+    pub avatar_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    #[validate(length(max = 500, message = "Bio must be at most 500 characters"))]
+    pub bio: Option<Option<String>>,
+}
+
+impl UpdateProfile {
+    /// Trims the fields; blank ones clear the field like `null` does.
+    pub fn normalize(&mut self) {
+        let fields = [&mut self.display_name, &mut self.avatar_url, &mut self.bio];
+        for value in fields.into_iter().flatten() {
+            *value = value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(String::from);
+        }
+    }
+}
+
+/// Avatars are shown by other clients, so only plain web links are accepted.
+fn validate_avatar_url(url: &str) -> Result<(), ValidationError> {
+    let valid = url.len() <= 2048
+        && (url.starts_with("https://") || url.starts_with("http://"))
+        && validator::ValidateUrl::validate_url(&url);
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_avatar_url")
+            .with_message("Avatar URL must be an http(s) URL of at most 2048 characters".into()))
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
     pub user: UserResponse,
 }
+
+/// Per-user settings, stored as JSON so new ones don't need a migration.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserPreferences {
+    /// Applied by `GET /items` when no `?sort=` is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_item_sort: Option<String>,
+}
+
+/// How long the token mailed to a new address stays valid.
+pub const EMAIL_CHANGE_EXPIRATION_HOURS: i64 = 24;
+
+/// Body of `PUT /auth/email`.
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangeEmail {
+    #[validate(email(message = "Invalid email format"))]
+    pub new_email: String,
+    #[validate(length(min = 8))]
+    pub current_password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailChangeResponse {
+    pub pending_email: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailChange {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IntrospectRequest {
+    pub token: String,
+}
+
+/// RFC 7662-style token description; only `active` is present for unusable tokens.
+#[derive(Debug, Default, Serialize)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>,
+}
+
+/// Outcome of `POST /auth/verify-token`. `error` names why the token would be
+/// refused; `claims` is present whenever the signature checked out.
+#[derive(Debug, Serialize)]
+pub struct TokenVerification {
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claims: Option<Claims>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MeQuery {
+    #[serde(default)]
+    pub stats: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserStats {
+    pub total_items: i64,
+    pub items_by_status: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MeResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<UserStats>,
+}
+
+pub const USER_ROLES: &[&str] = &[ROLE_USER, ROLE_ADMIN];
+
+/// `?sort=` values accepted by `GET /admin/users`, and the columns they order by.
+pub const USER_SORT_FIELDS: &[(&str, &str)] = &[
+    ("created_at", "created_at"),
+    ("email", "email"),
+    ("username", "username"),
+    ("last_login_at", "last_login_at"),
+];
+
+pub const DEFAULT_USER_SORT: &str = "-created_at";
+
+/// `GET /admin/users` filters beyond the shared list parameters.
+#[derive(Debug, Deserialize, Validate)]
+pub struct AdminUserFilter {
+    /// Matched against email and username, case-insensitively.
+    #[validate(length(min = 1, max = 100, message = "q must be between 1 and 100 characters"))]
+    pub q: Option<String>,
+    pub role: Option<String>,
+    pub is_active: Option<bool>,
+}
+
+/// Body of `PATCH /admin/users/:id`; absent fields are left alone.
+#[derive(Debug, Deserialize)]
+pub struct AdminUpdateUser {
+    pub role: Option<String>,
+    pub is_active: Option<bool>,
+}
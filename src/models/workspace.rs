@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utils::timestamp;
+
+/// Workspace roles, most privileged first. Owners manage members, editors
+/// change items, viewers only read them.
+pub const WORKSPACE_ROLES: &[&str] = &["owner", "editor", "viewer"];
+
+pub const WORKSPACE_OWNER: &str = "owner";
+
+/// Whether `role` may create, change and delete the workspace's items.
+pub fn can_write_items(role: &str) -> bool {
+    matches!(role, "owner" | "editor")
+}
+
+/// A workspace as seen by one member, with that member's role.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WorkspaceResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub personal: bool,
+    pub role: String,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWorkspace {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct WorkspaceMemberResponse {
+    pub user_id: Uuid,
+    pub username: String,
+    pub role: String,
+    #[serde(serialize_with = "timestamp::serialize")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddWorkspaceMember {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateWorkspaceMember {
+    pub role: String,
+}
@@ -0,0 +1,42 @@
+use std::{cell::Cell, future::Future};
+use tracing::{level_filters::LevelFilter, Event, Subscriber};
+use tracing_subscriber::{
+    filter::{Filtered, Targets},
+    layer::{Context, Layer},
+};
+
+tokio::task_local! {
+    static QUERY_COUNT: Cell<u32>;
+}
+
+/// Target of the event sqlx emits once for every statement it executes.
+const SQLX_QUERY_TARGET: &str = "sqlx::query";
+
+/// Runs `f` counting the database statements it executes, and returns the count
+/// with its output. Only statements run on this task are seen, so work handed
+/// to spawned tasks (or a streamed body produced after `f` returns) isn't counted.
+pub async fn count_queries<F: Future>(f: F) -> (F::Output, u32) {
+    QUERY_COUNT
+        .scope(Cell::new(0), async {
+            let output = f.await;
+            (output, QUERY_COUNT.with(Cell::get))
+        })
+        .await
+}
+
+/// Tracing layer behind `count_queries`. It gets sqlx's per-statement events at
+/// every level through its own filter, so they are counted without being logged
+/// (the log output keeps `LOG_FILTER`).
+pub fn layer<S: Subscriber>() -> Filtered<QueryCountLayer, Targets, S> {
+    QueryCountLayer.with_filter(Targets::new().with_target(SQLX_QUERY_TARGET, LevelFilter::TRACE))
+}
+
+pub struct QueryCountLayer;
+
+impl<S: Subscriber> Layer<S> for QueryCountLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if event.metadata().target() == SQLX_QUERY_TARGET {
+            let _ = QUERY_COUNT.try_with(|count| count.set(count.get() + 1));
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
+
+use crate::kv::KeyValueStore;
+
+/// Fixed-window request counter per user, kept in the shared key-value store.
+/// Each limiter counts under its own `name`, so several can limit the same user.
+///
+/// Without Redis the counts are per instance, so behind a load balancer each
+/// user effectively gets `limit` requests per window on every instance. A zero
+/// limit disables it.
+pub struct RateLimiter {
+    name: &'static str,
+    limit: u32,
+    window: Duration,
+    store: Arc<dyn KeyValueStore>,
+}
+
+impl RateLimiter {
+    pub fn new(
+        name: &'static str,
+        limit: u32,
+        window_secs: u64,
+        store: Arc<dyn KeyValueStore>,
+    ) -> Self {
+        Self {
+            name,
+            limit,
+            window: Duration::from_secs(window_secs),
+            store,
+        }
+    }
+
+    /// Counts a request against the user's current window. Over the limit, returns
+    /// how long until the window resets. Lets the request through if the store
+    /// is unavailable.
+    pub async fn check(&self, user_id: Uuid) -> Result<(), Duration> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+
+        let key = format!("rate_limit:{}:{}", self.name, user_id);
+        match self.store.increment(&key, self.window).await {
+            Ok((count, reset_in)) if count > u64::from(self.limit) => Err(reset_in),
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::warn!("Rate limit check failed: {}", e);
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,47 +1,656 @@
 use axum::{
+    error_handling::HandleErrorLayer,
+    extract::DefaultBodyLimit,
+    http::HeaderValue,
     middleware,
-    routing::{delete, get, post, put},
-    Extension, Router,
+    routing::{delete, get, patch, post, put, MethodRouter},
+    BoxError, Extension, Router,
 };
-use tower_http::cors::{Any, CorsLayer};
-use tower_http::trace::TraceLayer;
+use serde::Serialize;
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use tower::{load_shed::error::Overloaded, timeout::error::Elapsed, ServiceBuilder};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
 
 use crate::{
     config::Config,
-    handlers,
-    middleware::auth_middleware,
+    error::AppError,
+    handlers::{self, MULTIPART_OVERHEAD_BYTES},
+    middleware::{
+        auth_middleware, body_logging_middleware, cache_control_middleware, csrf_middleware,
+        database_breaker_middleware, deprecated, force_https_middleware, maintenance_middleware,
+        query_count_middleware, request_trace, require_admin, require_scope, security_headers,
+        timestamp_format_middleware, user_rate_limit_middleware,
+    },
+    utils::auth::{SCOPE_ITEMS_READ, SCOPE_ITEMS_WRITE},
     AppState,
 };
 
-pub fn create_router(state: AppState, config: Config) -> Router {
-    // CORS configuration
+pub fn create_router(state: AppState) -> Router {
+    // CORS configuration. Browsers never let a `*` in Access-Control-Allow-Headers
+    // cover `Authorization`, so echo back whatever the preflight asks for instead
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(AllowHeaders::mirror_request());
+
+    // Uploads stream past the default 2 MB body limit up to the attachment size cap
+    let attachment_body_limit = DefaultBodyLimit::max(
+        (state.config.attachment_max_bytes as usize).saturating_add(MULTIPART_OVERHEAD_BYTES),
+    );
 
     // Public routes (no authentication required)
-    let public_routes = Router::new()
-        .route("/health", get(handlers::health_check))
-        .route("/auth/signup", post(handlers::signup))
-        .route("/auth/login", post(handlers::login));
+    let (public_routes, public_info) = RouteTable::new("public", &state.config)
+        .route("/health", &["GET"], get(handlers::health_check))
+        .route("/health/ready", &["GET"], get(handlers::readiness_check))
+        .route("/version", &["GET"], get(handlers::version))
+        .route("/time", &["GET"], get(handlers::server_time))
+        .finish();
+
+    // Public auth routes, kept apart for their own CORS policy (see `auth_cors`)
+    let mut auth = RouteTable::new("public", &state.config)
+        .route("/auth/signup", &["POST"], post(handlers::signup))
+        .route("/auth/login", &["POST"], post(handlers::login))
+        .route("/auth/logout", &["POST"], post(handlers::logout))
+        .route("/auth/reactivate", &["POST"], post(handlers::reactivate))
+        .route("/auth/email/verify", &["POST"], post(handlers::verify_email_change));
+    // Token debugging without credentials; never registered in production
+    if !state.config.is_production() {
+        auth = auth.route("/auth/verify-token", &["POST"], post(handlers::verify_token_debug));
+    }
+    let (auth_routes, auth_info) = auth.finish();
 
     // Protected routes (authentication required)
-    let protected_routes = Router::new()
-        .route("/auth/me", get(handlers::get_me))
-        .route("/items", post(handlers::create_item))
-        .route("/items", get(handlers::get_items))
-        .route("/items/:id", get(handlers::get_item))
-        .route("/items/:id", put(handlers::update_item))
-        .route("/items/:id", delete(handlers::delete_item))
-        .layer(middleware::from_fn(auth_middleware));
-
-    // Combine routes
-    Router::new()
+    let mut protected = RouteTable::new("authenticated", &state.config)
+        .route(
+            "/auth/me",
+            &["GET", "PATCH"],
+            get(handlers::get_me).patch(handlers::update_profile),
+        )
+        // Old name of `/auth/me`, kept until clients have moved over
+        .route(
+            "/auth/profile",
+            &["GET"],
+            get(handlers::get_me).layer(deprecated("2027-06-30", "use GET /auth/me")),
+        )
+        .route("/auth/me/export", &["GET"], get(handlers::export_user_data))
+        .route("/auth/email", &["PUT"], put(handlers::request_email_change))
+        .route("/auth/deactivate", &["POST"], post(handlers::deactivate))
+        .route("/auth/invalidate-tokens", &["POST"], post(handlers::invalidate_tokens))
+        .route(
+            "/auth/preferences",
+            &["GET", "PUT"],
+            get(handlers::get_preferences).put(handlers::update_preferences),
+        )
+        .route(
+            "/auth/api-keys",
+            &["POST", "GET"],
+            post(handlers::create_api_key).get(handlers::get_api_keys),
+        )
+        .route("/auth/api-keys/:id", &["DELETE"], delete(handlers::delete_api_key))
+        .route(
+            "/items",
+            &["POST"],
+            post(handlers::create_item).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items",
+            &["GET"],
+            get(handlers::get_items).route_layer(require_scope(SCOPE_ITEMS_READ)),
+        )
+        .route(
+            "/items/batch",
+            &["POST", "DELETE"],
+            post(handlers::batch_create_items)
+                .delete(handlers::batch_delete_items)
+                .route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/batch-get",
+            &["POST"],
+            post(handlers::batch_get_items).route_layer(require_scope(SCOPE_ITEMS_READ)),
+        )
+        .route(
+            "/items/reorder",
+            &["POST"],
+            post(handlers::reorder_items).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/bulk-status",
+            &["POST"],
+            post(handlers::bulk_update_status).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/by-external-id/:ext",
+            &["PUT"],
+            put(handlers::upsert_item_by_external_id)
+                .route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/events",
+            &["GET"],
+            get(handlers::item_events).route_layer(require_scope(SCOPE_ITEMS_READ)),
+        )
+        .route(
+            "/items/:id",
+            &["GET"],
+            get(handlers::get_item).route_layer(require_scope(SCOPE_ITEMS_READ)),
+        )
+        .route(
+            "/items/:id",
+            &["PUT"],
+            put(handlers::update_item).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/:id",
+            &["PATCH"],
+            patch(handlers::patch_item).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/:id",
+            &["DELETE"],
+            delete(handlers::delete_item).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/:id/duplicate",
+            &["POST"],
+            post(handlers::duplicate_item).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/:id/archive",
+            &["POST"],
+            post(handlers::archive_item).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/:id/unarchive",
+            &["POST"],
+            post(handlers::unarchive_item).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .upload_route(
+            "/items/:id/attachments",
+            &["POST"],
+            post(handlers::upload_attachment)
+                .layer(attachment_body_limit)
+                .route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/:id/attachments",
+            &["GET"],
+            get(handlers::get_attachments).route_layer(require_scope(SCOPE_ITEMS_READ)),
+        )
+        .route(
+            "/items/:id/attachments/:attachment_id",
+            &["GET"],
+            get(handlers::download_attachment).route_layer(require_scope(SCOPE_ITEMS_READ)),
+        )
+        .route(
+            "/items/:id/comments",
+            &["POST"],
+            post(handlers::create_comment).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/items/:id/comments",
+            &["GET"],
+            get(handlers::get_comments).route_layer(require_scope(SCOPE_ITEMS_READ)),
+        )
+        .route(
+            "/items/:id/comments/:comment_id",
+            &["DELETE"],
+            delete(handlers::delete_comment).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        )
+        .route(
+            "/tags",
+            &["GET"],
+            get(handlers::get_tags).route_layer(require_scope(SCOPE_ITEMS_READ)),
+        )
+        .route(
+            "/tags/rename",
+            &["POST"],
+            post(handlers::rename_tag).route_layer(require_scope(SCOPE_ITEMS_WRITE)),
+        );
+    // Shared workspaces; without them everyone only has their personal workspace
+    if state.config.workspaces_enabled {
+        protected = protected
+            .route(
+                "/workspaces",
+                &["POST", "GET"],
+                post(handlers::create_workspace).get(handlers::get_workspaces),
+            )
+            .route(
+                "/workspaces/:id/members",
+                &["POST", "GET"],
+                post(handlers::add_workspace_member).get(handlers::get_workspace_members),
+            )
+            .route(
+                "/workspaces/:id/members/:user_id",
+                &["PUT", "DELETE"],
+                put(handlers::update_workspace_member).delete(handlers::remove_workspace_member),
+            );
+    }
+    let (protected_routes, protected_info) = protected.finish();
+    let protected_routes = protected_routes
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            user_rate_limit_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
+
+    // Admin routes (authentication + admin role required)
+    let (admin_routes, admin_info) = RouteTable::new("admin", &state.config)
+        .route(
+            "/admin/maintenance",
+            &["GET", "PUT"],
+            get(handlers::get_maintenance).put(handlers::set_maintenance),
+        )
+        .route("/admin/invites", &["POST"], post(handlers::create_invite))
+        .route("/admin/stats", &["GET"], get(handlers::get_stats))
+        .route("/admin/users", &["GET"], get(handlers::list_users))
+        .route("/admin/users/:id", &["PATCH"], patch(handlers::update_user))
+        .route("/auth/token/introspect", &["POST"], post(handlers::introspect_token))
+        .finish();
+    let admin_routes = admin_routes
+        .layer(middleware::from_fn(require_admin))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            user_rate_limit_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), auth_middleware));
+
+    // Route listing for onboarding; never registered in production
+    let mut router = Router::new();
+    if !state.config.is_production() {
+        let mut routes: Vec<RouteInfo> =
+            [public_info, auth_info, protected_info, admin_info].concat();
+        routes.push(RouteInfo {
+            method: "GET",
+            path: "/routes",
+            access: "public",
+        });
+        routes.sort_by(|a, b| (a.path, a.method).cmp(&(b.path, b.method)));
+        router = router.route(
+            "/routes",
+            get(handlers::list_routes).layer(Extension(Arc::new(routes))),
+        );
+    }
+
+    // Combine routes into two CORS groups. CORS must stay outside everything that
+    // can reject a request (auth, maintenance): preflight OPTIONS carry no
+    // credentials and are answered there. So each group gets the shared request
+    // middleware and then its own CORS layer; a request only ever passes through
+    // the CORS layer of its group. A CORS layer on the merged router would wrap
+    // both groups and answer every preflight before theirs, so there is none
+    let app_routes = router
         .merge(public_routes)
         .merge(protected_routes)
-        .layer(cors)
-        .layer(TraceLayer::new_for_http())
-        .layer(Extension(config))
-        .with_state(state)
+        .merge(admin_routes);
+    let auth_cors = auth_cors(&state.config).unwrap_or_else(|| cors.clone());
+    let app = Router::new()
+        .merge(with_request_middleware(auth_routes, &state).layer(auth_cors))
+        .merge(with_request_middleware(app_routes, &state).layer(cors))
+        .with_state(state.clone());
+    with_load_shedding(app, &state)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            force_https_middleware,
+        ))
+        .layer(security_headers(&state.config))
+        .layer(request_trace(&state.config))
+}
+
+/// Routes that skip the request queue, so an overloaded instance still reports on itself.
+const LOAD_SHED_EXEMPT_PATHS: &[&str] = &["/health", "/health/ready"];
+
+/// With `REQUEST_CONCURRENCY_LIMIT` set, runs that many requests at once and queues
+/// up to `REQUEST_QUEUE_DEPTH` more; further requests are shed with `503` and
+/// counted in `shed_requests`. The stack wraps the app as a single service, as
+/// `Router::layer` would give every route a queue and limit of its own.
+fn with_load_shedding(app: Router, state: &AppState) -> Router {
+    let limit = state.config.request_concurrency_limit;
+    if limit == 0 {
+        return app;
+    }
+
+    let shed_requests = state.shed_requests.clone();
+    let queued = ServiceBuilder::new()
+        .layer(HandleErrorLayer::new(move |error: BoxError| {
+            let shed_requests = shed_requests.clone();
+            async move {
+                if !error.is::<Overloaded>() {
+                    return AppError::Internal(format!("Request queue failed: {}", error));
+                }
+                shed_requests.fetch_add(1, Ordering::Relaxed);
+                AppError::ServiceUnavailable(
+                    "Server is overloaded, retry shortly".to_string(),
+                    Some(1),
+                )
+            }
+        }))
+        .load_shed()
+        .buffer(state.config.request_queue_depth)
+        .concurrency_limit(limit)
+        .service(app.clone());
+
+    // `route_service` refuses a `Router` as such, hence `into_service`
+    LOAD_SHED_EXEMPT_PATHS
+        .iter()
+        .fold(Router::new(), |router, &path| {
+            router.route_service(path, app.clone().into_service())
+        })
+        .fallback_service(queued)
+}
+
+/// Middleware every route goes through, innermost first, below its CORS layer.
+fn with_request_middleware(router: Router<AppState>, state: &AppState) -> Router<AppState> {
+    router
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            query_count_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            database_breaker_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            timestamp_format_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(state.clone(), csrf_middleware))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            maintenance_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            body_logging_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            cache_control_middleware,
+        ))
+}
+
+/// Stricter CORS for the public `/auth/*` routes when `AUTH_CORS_ORIGINS` is set:
+/// only those origins, with credentials, so a browser app there can receive and
+/// send the auth cookie. `None` leaves them on the default policy.
+fn auth_cors(config: &Config) -> Option<CorsLayer> {
+    if config.auth_cors_origins.is_empty() {
+        return None;
+    }
+
+    // Wildcards aren't allowed alongside credentials, so requested methods and
+    // headers are echoed back instead
+    // Each origin was checked to be a valid header value when the config was loaded
+    let origins = config
+        .auth_cors_origins
+        .iter()
+        .filter_map(|origin| HeaderValue::from_str(origin).ok());
+    Some(
+        CorsLayer::new()
+            .allow_origin(AllowOrigin::list(origins))
+            .allow_methods(AllowMethods::mirror_request())
+            .allow_headers(AllowHeaders::mirror_request())
+            .allow_credentials(true),
+    )
+}
+
+/// Only the wait for the response to start is limited; streamed bodies (exports,
+/// downloads, events) carry on.
+async fn request_timed_out(error: BoxError) -> AppError {
+    if error.is::<Elapsed>() {
+        AppError::ServiceUnavailable("Request timed out".to_string(), None)
+    } else {
+        AppError::Internal(format!("Request failed: {}", error))
+    }
+}
+
+/// One registered `METHOD path`, as listed by `GET /routes`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteInfo {
+    pub method: &'static str,
+    pub path: &'static str,
+    /// `public`, `authenticated` or `admin`.
+    pub access: &'static str,
+}
+
+/// Builds a group of routes sharing the same access level, recording each one
+/// so the API can describe itself. `methods` must name what `method_router`
+/// handles; axum keeps that opaque. Each route gets its own timeout: the one
+/// `REQUEST_TIMEOUTS` gives its path, or else `REQUEST_TIMEOUT` (none for uploads).
+struct RouteTable<'a> {
+    router: Router<AppState>,
+    access: &'static str,
+    routes: Vec<RouteInfo>,
+    config: &'a Config,
+}
+
+impl<'a> RouteTable<'a> {
+    fn new(access: &'static str, config: &'a Config) -> Self {
+        Self {
+            router: Router::new(),
+            access,
+            routes: Vec::new(),
+            config,
+        }
+    }
+
+    fn route(
+        self,
+        path: &'static str,
+        methods: &[&'static str],
+        method_router: MethodRouter<AppState>,
+    ) -> Self {
+        let default_secs = self.config.request_timeout_secs;
+        self.timed_route(path, methods, method_router, default_secs)
+    }
+
+    /// A route whose handler reads a large request body itself. Slow clients take
+    /// as long as they take, so only a `REQUEST_TIMEOUTS` entry limits it.
+    fn upload_route(
+        self,
+        path: &'static str,
+        methods: &[&'static str],
+        method_router: MethodRouter<AppState>,
+    ) -> Self {
+        self.timed_route(path, methods, method_router, 0)
+    }
+
+    fn timed_route(
+        mut self,
+        path: &'static str,
+        methods: &[&'static str],
+        method_router: MethodRouter<AppState>,
+        default_secs: u64,
+    ) -> Self {
+        let timeout_secs = self
+            .config
+            .request_timeouts
+            .iter()
+            .find(|(route, _)| route == path)
+            .map_or(default_secs, |&(_, secs)| secs);
+        let method_router = if timeout_secs == 0 {
+            method_router
+        } else {
+            method_router.layer(
+                ServiceBuilder::new()
+                    .layer(HandleErrorLayer::new(request_timed_out))
+                    .timeout(Duration::from_secs(timeout_secs)),
+            )
+        };
+
+        self.router = self.router.route(path, method_router);
+        self.routes.extend(methods.iter().map(|&method| RouteInfo {
+            method,
+            path,
+            access: self.access,
+        }));
+        self
+    }
+
+    fn finish(self) -> (Router<AppState>, Vec<RouteInfo>) {
+        (self.router, self.routes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support,
+        utils::auth::{all_scopes, Claims},
+    };
+    use axum::{
+        body::Body,
+        extract::ConnectInfo,
+        http::{header, Method, Request, StatusCode},
+        response::Response,
+    };
+    use std::{future::poll_fn, net::SocketAddr};
+    use tower::Service;
+    use uuid::Uuid;
+
+    /// Runs one request through the app as `axum::serve` would, from a local client.
+    async fn send(mut app: Router, mut request: Request<Body>) -> Response {
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 40000))));
+        poll_fn(|cx| Service::<Request<Body>>::poll_ready(&mut app, cx))
+            .await
+            .unwrap();
+        app.call(request).await.unwrap()
+    }
+
+    fn token_for(config: &Config) -> String {
+        let now = chrono::Utc::now().timestamp();
+        let user_id = Uuid::new_v4();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            uid: Some(user_id),
+            email: "pool@example.com".to_string(),
+            role: "user".to_string(),
+            scopes: all_scopes(),
+            aud: None,
+            ver: 0,
+            cfp: None,
+            exp: now + 60,
+            iat: now,
+        };
+        jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn exhausted_pool_answers_503_with_retry_after() {
+        let mut config = test_support::config();
+        config.verify_user_on_request = true;
+        config.bind_token_to_client = false;
+        config.jwt_audiences = Vec::new();
+        config.maintenance_mode = false;
+        // A single connection that never frees up
+        let db = test_support::stalled_pool(1, Duration::from_millis(200)).await;
+        let token = token_for(&config);
+        let app = create_router(test_support::state(config, db).await);
+
+        let request = || {
+            Request::get("/items")
+                .header(header::AUTHORIZATION, format!("Bearer {token}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (first, second) = tokio::join!(send(app.clone(), request()), send(app, request()));
+
+        for response in [first, second] {
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+        }
+    }
+
+    fn preflight(path: &str, origin: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri(path)
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(
+                header::ACCESS_CONTROL_REQUEST_HEADERS,
+                "authorization,content-type",
+            )
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    /// An app on the default CORS policy whose database is never reached.
+    async fn cors_app(configure: impl FnOnce(&mut Config)) -> Router {
+        let mut config = test_support::config();
+        config.force_https = false;
+        config.maintenance_mode = false;
+        config.auth_cors_origins = Vec::new();
+        configure(&mut config);
+        let db = test_support::stalled_pool(1, Duration::from_millis(200)).await;
+        create_router(test_support::state(config, db).await)
+    }
+
+    #[tokio::test]
+    async fn preflight_allows_any_origin_and_requested_headers() {
+        for path in [
+            "/items",
+            "/items/00000000-0000-0000-0000-000000000000",
+            "/auth/login",
+        ] {
+            let response = send(cors_app(|_| {}).await, preflight(path, "https://a.example")).await;
+
+            assert_eq!(response.status(), StatusCode::OK, "for {path}");
+            let headers = response.headers();
+            assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+            assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "*");
+            assert_eq!(
+                headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+                "authorization,content-type"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn preflight_is_answered_before_maintenance_and_auth() {
+        let app = cors_app(|config| config.maintenance_mode = true).await;
+        let response = send(app, preflight("/items", "https://a.example")).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    }
+
+    #[tokio::test]
+    async fn auth_cors_origins_limit_auth_preflights() {
+        let origin = "https://app.example.com";
+        let app = cors_app(|config| config.auth_cors_origins = vec![origin.to_string()]).await;
+
+        let response = send(app.clone(), preflight("/auth/login", origin)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], origin);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "POST");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "authorization,content-type"
+        );
+
+        let response = send(
+            app.clone(),
+            preflight("/auth/login", "https://evil.example"),
+        )
+        .await;
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        // Everything else keeps the open policy
+        let response = send(app, preflight("/items", "https://evil.example")).await;
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    }
 }
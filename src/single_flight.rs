@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+use crate::models::Item;
+
+/// Coalesces identical concurrent calls: while one caller is running the work
+/// for a key, others asking for the same key wait for its result instead of
+/// running it again.
+///
+/// Only calls that overlap in time share a result, so nothing is served after
+/// the leading call finishes. The cost is that a caller arriving mid-flight can
+/// get a result read just *before* a write it has already seen committed. A
+/// failed call shares nothing; each waiter then runs the work itself. When
+/// disabled, every call runs on its own.
+#[derive(Debug)]
+pub struct SingleFlight<K, V> {
+    enabled: bool,
+    in_flight: Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+}
+
+impl<K, V> SingleFlight<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn run<F, Fut, E>(&self, key: K, work: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        if !self.enabled {
+            return work().await;
+        }
+
+        let cell = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(key.clone())
+            .or_default()
+            .clone();
+        let mut retire = Retire {
+            in_flight: &self.in_flight,
+            key,
+            cell: Some(cell),
+            finished: false,
+        };
+
+        // If the leader is cancelled or fails, a waiter runs its own `work`
+        let cell = retire.cell.as_deref().expect("taken only on drop");
+        let result = cell.get_or_try_init(work).await.cloned();
+        retire.finished = true;
+
+        result
+    }
+}
+
+/// Retires a call's map entry when the call ends, including when it is cancelled.
+struct Retire<'a, K: Eq + Hash, V> {
+    in_flight: &'a Mutex<HashMap<K, Arc<OnceCell<V>>>>,
+    key: K,
+    cell: Option<Arc<OnceCell<V>>>,
+    finished: bool,
+}
+
+impl<K: Eq + Hash, V> Drop for Retire<'_, K, V> {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(cell) = self.cell.take() else {
+            return;
+        };
+
+        // The first caller to finish retires the entry so later calls start fresh. A
+        // cancelled caller only does when nobody else holds the cell (the map and this
+        // guard are the two references); otherwise its empty cell would stay forever.
+        // The reference is dropped under the lock so the last of several cancelled
+        // callers sees the others gone.
+        let retire = in_flight.get(&self.key).is_some_and(|current| {
+            Arc::ptr_eq(current, &cell) && (self.finished || Arc::strong_count(&cell) == 2)
+        });
+        drop(cell);
+        if retire {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+/// Identifies one `GET /items` page query.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ItemListKey {
+    pub user_id: Uuid,
+    pub workspace_id: Option<Uuid>,
+    pub status: Option<String>,
+    pub tag: Option<String>,
+    pub search: Option<String>,
+    pub due_before: Option<DateTime<Utc>>,
+    pub due_after: Option<DateTime<Utc>>,
+    pub completed_after: Option<DateTime<Utc>>,
+    pub overdue: Option<bool>,
+    pub archived: bool,
+    pub order_by: String,
+    pub per_page: i64,
+    pub offset: i64,
+}
+
+/// Total count and the page of items, each with its search rank when searching.
+pub type ItemListFlight = SingleFlight<ItemListKey, (i64, Arc<Vec<(Item, Option<f32>)>>)>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn in_flight(flight: &SingleFlight<u32, u32>) -> usize {
+        flight.in_flight.lock().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn cancelled_leader_leaves_no_entry() {
+        let flight = SingleFlight::new(true);
+        let pending = flight.run(1, std::future::pending::<Result<u32, ()>>);
+        assert!(tokio::time::timeout(Duration::from_millis(10), pending)
+            .await
+            .is_err());
+
+        assert_eq!(in_flight(&flight), 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_keeps_the_leaders_entry() {
+        let flight = SingleFlight::new(true);
+        let (release, released) = tokio::sync::oneshot::channel::<()>();
+        let leader = flight.run(1, || async {
+            released.await.ok();
+            Ok::<_, ()>(7)
+        });
+        tokio::pin!(leader);
+        assert!(poll_once(&mut leader).await.is_none());
+
+        let mut waiter = Box::pin(flight.run(1, || async { Ok::<_, ()>(8) }));
+        assert!(poll_once(&mut waiter).await.is_none());
+        drop(waiter);
+        assert_eq!(in_flight(&flight), 1);
+
+        release.send(()).unwrap();
+        assert_eq!(leader.await, Ok(7));
+        assert_eq!(in_flight(&flight), 0);
+    }
+
+    /// Polls a future once, returning its output if it was ready.
+    async fn poll_once<F: Future + Unpin>(future: &mut F) -> Option<F::Output> {
+        std::future::poll_fn(|cx| {
+            std::task::Poll::Ready(match std::pin::Pin::new(&mut *future).poll(cx) {
+                std::task::Poll::Ready(output) => Some(output),
+                std::task::Poll::Pending => None,
+            })
+        })
+        .await
+    }
+}
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncWriteExt};
+
+use super::{validate_key, Storage, StorageError, StorageReader};
+
+/// Stores objects as files under a root directory, one file per key.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(
+        &self,
+        key: &str,
+        _content_type: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+    ) -> Result<u64, StorageError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = tokio::fs::File::create(&path).await?;
+        let written = match tokio::io::copy(reader, &mut file).await {
+            Ok(written) => written,
+            Err(e) => {
+                // Don't leave a truncated object behind
+                drop(file);
+                let _ = tokio::fs::remove_file(&path).await;
+                return Err(e.into());
+            }
+        };
+        file.flush().await?;
+
+        Ok(written)
+    }
+
+    async fn get(&self, key: &str) -> Result<StorageReader, StorageError> {
+        match tokio::fs::File::open(self.path_for(key)?).await {
+            Ok(file) => Ok(Box::pin(file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(StorageError::NotFound(key.to_string()))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match tokio::fs::remove_file(self.path_for(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
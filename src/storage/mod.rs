@@ -0,0 +1,80 @@
+pub mod local;
+pub mod s3;
+
+pub use local::*;
+pub use s3::*;
+
+use async_trait::async_trait;
+use std::{pin::Pin, sync::Arc};
+use tokio::io::AsyncRead;
+
+use crate::config::{Config, StorageBackend};
+
+/// Object contents streamed back from a [`Storage`] backend.
+pub type StorageReader = Pin<Box<dyn AsyncRead + Send>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("Object not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid storage key: {0}")]
+    InvalidKey(String),
+
+    #[error("Storage I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+}
+
+/// Blob storage addressed by opaque keys. Keys are chosen by the caller and
+/// must be relative, `/`-separated paths without `.` or `..` segments.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Writes everything `reader` yields under `key`, replacing any existing
+    /// object, and returns the number of bytes stored.
+    async fn put(
+        &self,
+        key: &str,
+        content_type: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+    ) -> Result<u64, StorageError>;
+
+    async fn get(&self, key: &str) -> Result<StorageReader, StorageError>;
+
+    /// Removes the object; deleting a missing key is not an error.
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// A time-limited URL clients can download the object from directly, for
+    /// backends that support one.
+    async fn presigned_url(
+        &self,
+        _key: &str,
+        _expires_in_secs: u64,
+    ) -> Result<Option<String>, StorageError> {
+        Ok(None)
+    }
+}
+
+/// Builds the backend selected by `STORAGE_BACKEND`.
+pub fn from_config(config: &Config) -> anyhow::Result<Arc<dyn Storage>> {
+    match config.storage_backend {
+        StorageBackend::Local => Ok(Arc::new(LocalStorage::new(&config.attachments_dir))),
+        StorageBackend::S3 => Ok(Arc::new(S3Storage::from_config(config)?)),
+    }
+}
+
+fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && !key.starts_with('/')
+        && key.split('/').all(|segment| {
+            !segment.is_empty() && segment != "." && segment != ".." && !segment.contains('\\')
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey(key.to_string()))
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use s3::{creds::Credentials, error::S3Error, Bucket, Region};
+use tokio::io::AsyncRead;
+use tokio_stream::StreamExt;
+use tokio_util::io::StreamReader;
+
+use super::{validate_key, Storage, StorageError, StorageReader};
+use crate::config::Config;
+
+/// Longest validity S3 accepts for a presigned URL (one week).
+const MAX_PRESIGN_EXPIRY_SECS: u64 = 7 * 24 * 60 * 60;
+
+/// Stores objects in an S3 bucket or any S3-compatible service (MinIO, R2, ...).
+pub struct S3Storage {
+    bucket: Box<Bucket>,
+}
+
+impl S3Storage {
+    /// Connects to `S3_BUCKET`. Credentials come from the standard
+    /// `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` variables.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let name = config
+            .s3_bucket
+            .as_deref()
+            .context("S3_BUCKET must be set when STORAGE_BACKEND=s3")?;
+        let region = match &config.s3_endpoint {
+            Some(endpoint) => Region::Custom {
+                region: config.s3_region.clone(),
+                endpoint: endpoint.clone(),
+            },
+            None => config
+                .s3_region
+                .parse()
+                .context("S3_REGION must be a valid region")?,
+        };
+        let credentials = Credentials::from_env().context(
+            "AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set when STORAGE_BACKEND=s3",
+        )?;
+
+        let mut bucket = Bucket::new(name, region, credentials)?;
+        if config.s3_path_style {
+            bucket = bucket.with_path_style();
+        }
+
+        Ok(Self { bucket })
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(
+        &self,
+        key: &str,
+        content_type: &str,
+        reader: &mut (dyn AsyncRead + Send + Unpin),
+    ) -> Result<u64, StorageError> {
+        validate_key(key)?;
+        let mut reader = reader;
+        let response = self
+            .bucket
+            .put_object_stream_with_content_type(&mut reader, key, content_type)
+            .await
+            .map_err(map_s3_error(key))?;
+
+        Ok(response.uploaded_bytes() as u64)
+    }
+
+    async fn get(&self, key: &str) -> Result<StorageReader, StorageError> {
+        validate_key(key)?;
+        let response = self
+            .bucket
+            .get_object_stream(key)
+            .await
+            .map_err(map_s3_error(key))?;
+
+        let bytes = response
+            .bytes
+            .map(|chunk| chunk.map_err(std::io::Error::other));
+        Ok(Box::pin(StreamReader::new(bytes)))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        match self.bucket.delete_object(key).await {
+            Err(S3Error::HttpFailWithBody(404, _)) | Ok(_) => Ok(()),
+            Err(e) => Err(StorageError::Backend(e.to_string())),
+        }
+    }
+
+    async fn presigned_url(
+        &self,
+        key: &str,
+        expires_in_secs: u64,
+    ) -> Result<Option<String>, StorageError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .presign_get(
+                key,
+                expires_in_secs.min(MAX_PRESIGN_EXPIRY_SECS) as u32,
+                None,
+            )
+            .await
+            .map_err(map_s3_error(key))?;
+
+        Ok(Some(url))
+    }
+}
+
+fn map_s3_error(key: &str) -> impl FnOnce(S3Error) -> StorageError + '_ {
+    move |e| match e {
+        S3Error::HttpFailWithBody(404, _) => StorageError::NotFound(key.to_string()),
+        e => StorageError::Backend(e.to_string()),
+    }
+}
@@ -0,0 +1,59 @@
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::{sync::watch, task::JoinHandle};
+
+/// Tables holding expiring rows, and the condition that marks a row as stale.
+/// Tombstones and departures are kept for `ITEM_TOMBSTONE_RETENTION_DAYS`.
+const EXPIRING_TABLES: &[(&str, &str)] = &[
+    ("api_keys", "expires_at IS NOT NULL AND expires_at < NOW()"),
+    ("email_changes", "expires_at < NOW()"),
+    ("invites", "expires_at < NOW()"),
+    ("item_tombstones", "deleted_at < NOW() - INTERVAL '90 days'"),
+    (
+        "workspace_departures",
+        "left_at < NOW() - INTERVAL '90 days'",
+    ),
+];
+
+/// Periodically deletes expired rows so the auth and tombstone tables don't grow unbounded.
+pub fn spawn_cleanup(
+    pool: PgPool,
+    interval_secs: u64,
+    mut shutdown: watch::Receiver<bool>,
+) -> Option<JoinHandle<()>> {
+    if interval_secs == 0 {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => purge_expired(&pool).await,
+                _ = shutdown.changed() => break,
+            }
+        }
+
+        tracing::debug!("Cleanup task stopped");
+    }))
+}
+
+async fn purge_expired(pool: &PgPool) {
+    for (table, condition) in EXPIRING_TABLES {
+        let query = format!("DELETE FROM {} WHERE {}", table, condition);
+
+        // A failure on one table shouldn't stop the others or the task
+        match sqlx::query(&query).execute(pool).await {
+            Ok(result) if result.rows_affected() > 0 => {
+                tracing::info!(
+                    table,
+                    purged = result.rows_affected(),
+                    "Purged expired rows"
+                );
+            }
+            Ok(_) => tracing::debug!(table, "No expired rows to purge"),
+            Err(e) => tracing::error!(table, "Failed to purge expired rows: {:?}", e),
+        }
+    }
+}
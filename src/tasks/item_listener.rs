@@ -0,0 +1,79 @@
+use sqlx::{postgres::PgListener, PgPool};
+use std::time::Duration;
+use tokio::{
+    sync::{broadcast, watch},
+    task::JoinHandle,
+};
+
+use crate::events::{ItemEvent, ITEMS_CHANNEL};
+
+/// Delay before retrying after the listener connection fails.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Listens on `items_channel` over a dedicated connection and re-broadcasts each
+/// notification to this instance's subscribers, so item events reach clients no
+/// matter which instance handled the mutation.
+pub fn spawn_item_listener(
+    pool: PgPool,
+    enabled: bool,
+    events: broadcast::Sender<ItemEvent>,
+    mut shutdown: watch::Receiver<bool>,
+) -> Option<JoinHandle<()>> {
+    if !enabled {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        let mut listener: Option<PgListener> = None;
+
+        loop {
+            // Connect on startup and again after an error; a plain dropped
+            // connection is re-established by PgListener itself
+            let active = match listener.as_mut() {
+                Some(active) => active,
+                None => match connect(&pool).await {
+                    Ok(connected) => listener.insert(connected),
+                    Err(e) => {
+                        tracing::error!("Failed to listen on {}: {:?}", ITEMS_CHANNEL, e);
+                        tokio::select! {
+                            _ = tokio::time::sleep(RECONNECT_DELAY) => continue,
+                            _ = shutdown.changed() => break,
+                        }
+                    }
+                },
+            };
+
+            tokio::select! {
+                notification = active.recv() => match notification {
+                    Ok(notification) => {
+                        match serde_json::from_str::<ItemEvent>(notification.payload()) {
+                            // No local subscribers is not an error
+                            Ok(event) => {
+                                let _ = events.send(event);
+                            }
+                            Err(e) => tracing::warn!("Ignoring malformed item event: {:?}", e),
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Item listener error: {:?}", e);
+                        listener = None;
+                        tokio::select! {
+                            _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+                            _ = shutdown.changed() => break,
+                        }
+                    }
+                },
+                _ = shutdown.changed() => break,
+            }
+        }
+
+        tracing::debug!("Item listener task stopped");
+    }))
+}
+
+async fn connect(pool: &PgPool) -> sqlx::Result<PgListener> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(ITEMS_CHANNEL).await?;
+    tracing::info!("Listening for item events on {}", ITEMS_CHANNEL);
+    Ok(listener)
+}
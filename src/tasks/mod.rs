@@ -0,0 +1,7 @@
+pub mod cleanup;
+pub mod item_listener;
+pub mod pool_stats;
+
+pub use cleanup::*;
+pub use item_listener::*;
+pub use pool_stats::*;
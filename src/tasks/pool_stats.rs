@@ -0,0 +1,36 @@
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::{sync::watch, task::JoinHandle};
+
+/// Periodically logs connection pool usage to help diagnose leaks and acquire timeouts.
+pub fn spawn_pool_stats(
+    pool: PgPool,
+    interval_secs: u64,
+    mut shutdown: watch::Receiver<bool>,
+) -> Option<JoinHandle<()>> {
+    if interval_secs == 0 {
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    let size = pool.size();
+                    let idle = pool.num_idle() as u32;
+                    tracing::debug!(
+                        size,
+                        idle,
+                        in_use = size.saturating_sub(idle),
+                        "Database pool stats"
+                    );
+                }
+                _ = shutdown.changed() => break,
+            }
+        }
+
+        tracing::debug!("Pool stats task stopped");
+    }))
+}
@@ -0,0 +1,57 @@
+use sqlx::{postgres::PgPoolOptions, PgPool};
+use std::{sync::OnceLock, time::Duration};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, watch},
+};
+
+use crate::{config::Config, events::ITEM_EVENTS_CAPACITY, AppState};
+
+/// Configuration loaded the way the server loads it, once per test run. Tests
+/// change the fields they depend on on their own copy.
+pub fn config() -> Config {
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+
+    CONFIG
+        .get_or_init(|| {
+            // Nothing connects to it; see `stalled_pool`
+            if std::env::var_os("DATABASE_URL").is_none() {
+                std::env::set_var("DATABASE_URL", "postgres://postgres@127.0.0.1/unused");
+            }
+            Config::from_env().expect("test configuration must load")
+        })
+        .clone()
+}
+
+/// A pool of `max_connections` that never hands one out: it connects to a local
+/// socket that accepts connections but never answers the Postgres handshake, so
+/// every query waits `acquire_timeout` and fails with `PoolTimedOut`. Tests that
+/// shouldn't reach the database get a clear failure instead of a hang.
+pub async fn stalled_pool(max_connections: u32, acquire_timeout: Duration) -> PgPool {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut accepted = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            accepted.push(socket);
+        }
+    });
+
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .acquire_timeout(acquire_timeout)
+        .connect_lazy(&format!("postgres://postgres@127.0.0.1:{port}/unused"))
+        .unwrap()
+}
+
+/// Application state over `db`, built as the server builds it.
+pub async fn state(config: Config, db: PgPool) -> AppState {
+    let (item_events, _) = broadcast::channel(ITEM_EVENTS_CAPACITY);
+    // Left unsent for the life of the test run
+    let (shutdown_tx, shutdown) = watch::channel(false);
+    std::mem::forget(shutdown_tx);
+
+    AppState::new(config, db, item_events, shutdown)
+        .await
+        .expect("test state must build")
+}
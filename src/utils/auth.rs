@@ -1,33 +1,103 @@
-use crate::config::Config;
+use crate::{
+    config::{Config, JwtSubject},
+    models::{User, ROLE_USER},
+};
+use axum::http::{header, HeaderMap};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+pub const SCOPE_ITEMS_READ: &str = "items:read";
+pub const SCOPE_ITEMS_WRITE: &str = "items:write";
+pub const ALL_SCOPES: &[&str] = &[SCOPE_ITEMS_READ, SCOPE_ITEMS_WRITE];
+
+/// Value chosen by the client (say, a random id kept in app storage) that goes
+/// into the client fingerprint with `BIND_TOKEN_TO_CLIENT`.
+pub const CLIENT_ID_HEADER: &str = "x-client-id";
+
+/// Marks API keys so they are recognisable in logs and secret scanners.
+const API_KEY_PREFIX: &str = "rbs_";
+const API_KEY_RANDOM_LEN: usize = 40;
+/// Number of leading characters of a key kept in clear text for identification.
+const API_KEY_DISPLAY_LEN: usize = 12;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
-    pub sub: String, // User ID
+    pub sub: String, // User ID or email, depending on JWT_SUBJECT
+    // The user's id whatever `sub` holds, since an email can pass to another account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<Uuid>,
     pub email: String,
+    #[serde(default = "default_role")]
+    pub role: String,
+    // Tokens issued before scopes existed carry every scope
+    #[serde(default = "all_scopes")]
+    pub scopes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aud: Option<Audience>,
+    // Must match the user's current `token_version`; tokens issued before versioning are 0
+    #[serde(default)]
+    pub ver: i32,
+    // Fingerprint of the client the token was issued to, set with BIND_TOKEN_TO_CLIENT
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cfp: Option<String>,
     pub exp: i64,
     pub iat: i64,
 }
 
+/// The `aud` claim, which RFC 7519 allows as one string or an array of them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+fn default_role() -> String {
+    ROLE_USER.to_string()
+}
+
+pub fn all_scopes() -> Vec<String> {
+    ALL_SCOPES.iter().map(|scope| scope.to_string()).collect()
+}
+
 impl Claims {
-    pub fn new(user_id: Uuid, email: String, config: &Config) -> Self {
+    pub fn new(user: &User, config: &Config, headers: &HeaderMap) -> Self {
         let now = Utc::now();
         let expiration = now + Duration::seconds(config.jwt_expiration);
 
         Self {
-            sub: user_id.to_string(),
-            email,
+            sub: match config.jwt_subject {
+                JwtSubject::Id => user.id.to_string(),
+                JwtSubject::Email => user.email.clone(),
+            },
+            uid: Some(user.id),
+            email: user.email.clone(),
+            role: user.role.clone(),
+            scopes: all_scopes(),
+            // The first configured audience is the default one stamped on new tokens
+            aud: config.jwt_audiences.first().cloned().map(Audience::One),
+            ver: user.token_version,
+            cfp: config.bind_token_to_client.then(|| client_fingerprint(headers)),
             exp: expiration.timestamp(),
             iat: now.timestamp(),
         }
     }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
 }
 
-pub fn create_token(user_id: Uuid, email: String, config: &Config) -> Result<String, jsonwebtoken::errors::Error> {
-    let claims = Claims::new(user_id, email, config);
+pub fn create_token(
+    user: &User,
+    config: &Config,
+    headers: &HeaderMap,
+) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = Claims::new(user, config, headers);
     
     encode(
         &Header::default(),
@@ -37,19 +107,139 @@ pub fn create_token(user_id: Uuid, email: String, config: &Config) -> Result<Str
 }
 
 pub fn verify_token(token: &str, config: &Config) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let mut validation = Validation::default();
+    if config.jwt_audiences.is_empty() {
+        validation.validate_aud = false;
+    } else {
+        validation.set_audience(&config.jwt_audiences);
+        validation.set_required_spec_claims(&["exp", "aud"]);
+    }
+
     let token_data = decode::<Claims>(
         token,
         &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
-        &Validation::default(),
+        &validation,
     )?;
 
     Ok(token_data.claims)
 }
 
-pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
-    bcrypt::hash(password, bcrypt::DEFAULT_COST)
+/// Digest of the request's `User-Agent` and `X-Client-Id`, either of which may be
+/// missing. Header values can't contain NUL, so it separates the two unambiguously.
+pub fn client_fingerprint(headers: &HeaderMap) -> String {
+    let value = |name: &str| headers.get(name).map_or(&[][..], |value| value.as_bytes());
+
+    let mut hasher = Sha256::new();
+    hasher.update(value(header::USER_AGENT.as_str()));
+    hasher.update([0]);
+    hasher.update(value(CLIENT_ID_HEADER));
+
+    format!("{:x}", hasher.finalize())
+}
+
+/// bcrypt is deliberately slow (hundreds of milliseconds at the default cost), so hashing
+/// and verifying run on the blocking pool instead of stalling a runtime worker.
+pub async fn hash_password(password: &str, cost: u32) -> Result<String, bcrypt::BcryptError> {
+    let password = password.to_string();
+    run_blocking(move || bcrypt::hash(password, cost)).await
 }
 
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, bcrypt::BcryptError> {
-    bcrypt::verify(password, hash)
+/// Whether `hash` was made with a lower bcrypt cost than `cost` (read from the
+/// `$2b$<cost>$` prefix) and should be replaced once the password is known.
+pub fn needs_rehash(hash: &str, cost: u32) -> bool {
+    hash.split('$')
+        .nth(2)
+        .and_then(|hash_cost| hash_cost.parse::<u32>().ok())
+        .is_some_and(|hash_cost| hash_cost < cost)
+}
+
+pub async fn verify_password(password: &str, hash: &str) -> Result<bool, bcrypt::BcryptError> {
+    let (password, hash) = (password.to_string(), hash.to_string());
+    run_blocking(move || bcrypt::verify(password, &hash)).await
+}
+
+/// Blocking tasks are never aborted, so the only join error is a panic, which is re-raised.
+async fn run_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// Generates a random alphanumeric token suitable for one-time links and keys.
+pub fn generate_token(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Generates a new API key, returning the full key and its displayable prefix.
+pub fn generate_api_key() -> (String, String) {
+    let key = format!("{}{}", API_KEY_PREFIX, generate_token(API_KEY_RANDOM_LEN));
+    let prefix = key[..API_KEY_DISPLAY_LEN].to_string();
+
+    (key, prefix)
+}
+
+/// Generated tokens are high-entropy, so a fast SHA-256 digest is sufficient for storage.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn token_with_aud(config: &Config, aud: serde_json::Value) -> String {
+        let now = Utc::now().timestamp();
+        let claims = serde_json::json!({
+            "sub": Uuid::new_v4().to_string(),
+            "email": "aud@example.com",
+            "aud": aud,
+            "exp": now + 60,
+            "iat": now,
+        });
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn config_with_audiences(audiences: &[&str]) -> Config {
+        let mut config = test_support::config();
+        config.jwt_audiences = audiences.iter().map(|aud| aud.to_string()).collect();
+        config
+    }
+
+    #[test]
+    fn accepts_single_audience_string() {
+        let config = config_with_audiences(&["api"]);
+        let claims = verify_token(&token_with_aud(&config, "api".into()), &config).unwrap();
+
+        assert_eq!(claims.aud, Some(Audience::One("api".to_string())));
+    }
+
+    #[test]
+    fn accepts_audience_array_containing_a_configured_audience() {
+        let config = config_with_audiences(&["api"]);
+        let token = token_with_aud(&config, serde_json::json!(["billing", "api"]));
+        let claims = verify_token(&token, &config).unwrap();
+
+        assert_eq!(
+            claims.aud,
+            Some(Audience::Many(vec!["billing".to_string(), "api".to_string()]))
+        );
+    }
+
+    #[test]
+    fn rejects_audience_array_without_a_configured_audience() {
+        let config = config_with_audiences(&["api"]);
+        let token = token_with_aud(&config, serde_json::json!(["billing", "admin"]));
+
+        assert!(verify_token(&token, &config).is_err());
+    }
 }
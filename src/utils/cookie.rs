@@ -0,0 +1,97 @@
+use axum::{
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponseParts, ResponseParts},
+};
+use std::convert::Infallible;
+
+use crate::{config::Config, utils::auth::generate_token};
+
+/// Readable by the page's JavaScript, which echoes it in [`CSRF_HEADER`].
+pub const CSRF_COOKIE_NAME: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+const CSRF_TOKEN_LEN: usize = 32;
+
+/// `Set-Cookie` headers for the access token and its CSRF token when
+/// `AUTH_COOKIE_ENABLED` is on; adds nothing to the response otherwise.
+pub struct AuthCookie(Vec<HeaderValue>);
+
+impl AuthCookie {
+    /// Stores `token` in an httpOnly cookie that expires with the token, next
+    /// to a fresh CSRF token for the double-submit check.
+    pub fn issue(config: &Config, token: &str) -> Self {
+        Self::build(
+            config,
+            token,
+            &generate_token(CSRF_TOKEN_LEN),
+            config.jwt_expiration,
+        )
+    }
+
+    /// Overwrites both cookies with empty, already expired ones.
+    pub fn clear(config: &Config) -> Self {
+        Self::build(config, "", "", 0)
+    }
+
+    fn build(config: &Config, token: &str, csrf_token: &str, max_age: i64) -> Self {
+        if !config.auth_cookie_enabled {
+            return Self(Vec::new());
+        }
+
+        let cookies = [
+            (config.auth_cookie_name.as_str(), token, true),
+            (CSRF_COOKIE_NAME, csrf_token, false),
+        ];
+
+        // Names are validated at startup and values are base64url or
+        // alphanumeric, so building the headers can't fail
+        Self(
+            cookies
+                .into_iter()
+                .filter_map(|(name, value, http_only)| {
+                    HeaderValue::from_str(&set_cookie(config, name, value, max_age, http_only)).ok()
+                })
+                .collect(),
+        )
+    }
+}
+
+impl IntoResponseParts for AuthCookie {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut res: ResponseParts) -> Result<ResponseParts, Self::Error> {
+        for cookie in self.0 {
+            res.headers_mut().append(header::SET_COOKIE, cookie);
+        }
+        Ok(res)
+    }
+}
+
+fn set_cookie(config: &Config, name: &str, value: &str, max_age: i64, http_only: bool) -> String {
+    let mut cookie = format!(
+        "{}={}; Path=/; Max-Age={}; SameSite={}",
+        name,
+        value,
+        max_age,
+        config.auth_cookie_same_site.as_str()
+    );
+    if http_only {
+        cookie.push_str("; HttpOnly");
+    }
+    if config.auth_cookie_secure {
+        cookie.push_str("; Secure");
+    }
+    cookie
+}
+
+/// Value of the first cookie called `name` across all `Cookie` headers.
+pub fn get_cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.trim_matches('"'))
+}
@@ -0,0 +1,14 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// IMF-fixdate, the preferred HTTP-date format (RFC 9110 §5.6.7).
+const HTTP_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+pub fn format_http_date(date: DateTime<Utc>) -> String {
+    date.format(HTTP_DATE_FORMAT).to_string()
+}
+
+pub fn parse_http_date(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(value.trim(), HTTP_DATE_FORMAT)
+        .ok()
+        .map(|naive| naive.and_utc())
+}
@@ -0,0 +1,14 @@
+use pulldown_cmark::{html, Options, Parser};
+
+/// Renders Markdown to HTML that is safe to embed in a page. Raw HTML in the
+/// source is passed through the sanitizer, which drops scripts, styles, event
+/// handler attributes and `javascript:` URLs, and marks links `noopener`.
+pub fn render_markdown(text: &str) -> String {
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+
+    let mut unsafe_html = String::with_capacity(text.len() * 3 / 2);
+    html::push_html(&mut unsafe_html, Parser::new_ext(text, options));
+
+    ammonia::clean(&unsafe_html)
+}
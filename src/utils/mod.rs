@@ -1 +1,6 @@
 pub mod auth;
+pub mod cookie;
+pub mod http;
+pub mod markdown;
+pub mod timestamp;
+pub mod username;
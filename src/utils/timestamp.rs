@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use std::future::Future;
+
+use crate::config::TimestampFormat;
+
+tokio::task_local! {
+    static TIMESTAMP_FORMAT: TimestampFormat;
+}
+
+/// Runs `f` with response timestamps written in `format`.
+pub async fn with_timestamp_format<F: Future>(format: TimestampFormat, f: F) -> F::Output {
+    TIMESTAMP_FORMAT.scope(format, f).await
+}
+
+/// Runs `f` with timestamps written as RFC 3339 whatever the request asked for, for
+/// payloads the server reads back itself.
+pub fn with_rfc3339<R>(f: impl FnOnce() -> R) -> R {
+    TIMESTAMP_FORMAT.sync_scope(TimestampFormat::Rfc3339, f)
+}
+
+/// The format timestamps are written in for the current request.
+pub fn current_format() -> TimestampFormat {
+    TIMESTAMP_FORMAT
+        .try_with(|format| *format)
+        .unwrap_or(TimestampFormat::Rfc3339)
+}
+
+/// `serialize_with` for response timestamps: RFC 3339 unless the request runs
+/// under `with_timestamp_format(TimestampFormat::EpochMillis, ..)`. Anything
+/// serialized outside a request (cache entries) stays RFC 3339; work that outlives
+/// the handler, like streamed bodies, has to carry the format along.
+pub fn serialize<S: Serializer>(at: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    match current_format() {
+        TimestampFormat::EpochMillis => serializer.serialize_i64(at.timestamp_millis()),
+        TimestampFormat::Rfc3339 => at.serialize(serializer),
+    }
+}
+
+/// `serialize_with` for optional response timestamps, in the same format as `serialize`.
+pub fn serialize_option<S: Serializer>(
+    at: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match at {
+        Some(at) => serialize(at, serializer),
+        None => serializer.serialize_none(),
+    }
+}
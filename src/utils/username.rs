@@ -0,0 +1,136 @@
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::UsernameCharset;
+
+/// Characters that render as nothing, so two usernames differing only by them
+/// look identical.
+const INVISIBLE_CHARS: &[char] = &[
+    '\u{00AD}', '\u{034F}', '\u{061C}', '\u{115F}', '\u{1160}', '\u{17B4}', '\u{17B5}', '\u{180E}',
+    '\u{200B}', '\u{200C}', '\u{200D}', '\u{200E}', '\u{200F}', '\u{202A}', '\u{202B}', '\u{202C}',
+    '\u{202D}', '\u{202E}', '\u{2060}', '\u{2061}', '\u{2062}', '\u{2063}', '\u{2064}', '\u{2066}',
+    '\u{2067}', '\u{2068}', '\u{2069}', '\u{3164}', '\u{FEFF}', '\u{FFA0}',
+];
+
+/// Punctuation allowed in `ascii` usernames besides letters and digits.
+const ASCII_PUNCTUATION: &[char] = &['.', '_', '-'];
+
+/// The form usernames are stored and compared in: trimmed, NFC-normalized so
+/// composed and decomposed accents match, and lowercased when `case_fold` is set.
+/// Fails with the reason when a character isn't allowed by `charset`.
+pub fn normalize_username(
+    username: &str,
+    charset: UsernameCharset,
+    case_fold: bool,
+) -> Result<String, String> {
+    let normalized: String = username.trim().nfc().collect();
+
+    let rejected = normalized.chars().find(|&c| match charset {
+        UsernameCharset::Ascii => !(c.is_ascii_alphanumeric() || ASCII_PUNCTUATION.contains(&c)),
+        // Spaces are fine between words, other whitespace (tabs, no-break spaces) isn't
+        UsernameCharset::Unicode => {
+            c.is_control() || INVISIBLE_CHARS.contains(&c) || (c.is_whitespace() && c != ' ')
+        }
+    });
+    if let Some(c) = rejected {
+        return Err(match charset {
+            UsernameCharset::Ascii => format!(
+                "Username may only contain ASCII letters, digits, '.', '_' and '-' (found {:?})",
+                c
+            ),
+            UsernameCharset::Unicode => format!(
+                "Username must not contain control or invisible characters (found U+{:04X})",
+                c as u32
+            ),
+        });
+    }
+
+    Ok(if case_fold {
+        normalized.to_lowercase()
+    } else {
+        normalized
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unicode(username: &str) -> Result<String, String> {
+        normalize_username(username, UsernameCharset::Unicode, false)
+    }
+
+    fn ascii(username: &str) -> Result<String, String> {
+        normalize_username(username, UsernameCharset::Ascii, false)
+    }
+
+    #[test]
+    fn trims_surrounding_whitespace() {
+        assert_eq!(unicode("alice  ").unwrap(), "alice");
+        assert_eq!(unicode("\t alice\n").unwrap(), "alice");
+        assert_eq!(ascii(" bob ").unwrap(), "bob");
+    }
+
+    #[test]
+    fn keeps_single_spaces_between_words() {
+        assert_eq!(unicode("Ada Lovelace").unwrap(), "Ada Lovelace");
+        assert!(unicode("Ada\u{00A0}Lovelace").is_err());
+        assert!(unicode("Ada\tLovelace").is_err());
+    }
+
+    #[test]
+    fn composed_and_decomposed_forms_match() {
+        let composed = unicode("Jos\u{00E9}").unwrap();
+        let decomposed = unicode("Jose\u{0301}").unwrap();
+        assert_eq!(composed, decomposed);
+        assert_eq!(decomposed.chars().count(), 4);
+    }
+
+    #[test]
+    fn rejects_zero_width_characters() {
+        for username in [
+            "ali\u{200B}ce",
+            "ali\u{200D}ce",
+            "\u{FEFF}alice",
+            "ali\u{2060}ce",
+        ] {
+            assert!(unicode(username).is_err(), "{username:?} was accepted");
+            assert!(ascii(username).is_err(), "{username:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn rejects_direction_overrides() {
+        for username in ["\u{202E}ecila", "alice\u{202D}", "al\u{2067}ice\u{2069}"] {
+            let error = unicode(username).unwrap_err();
+            assert!(error.contains("invisible"), "{error}");
+        }
+    }
+
+    #[test]
+    fn rejects_control_characters() {
+        for username in ["ali\u{0007}ce", "ali\u{0000}ce", "ali\nce", "ali\u{0085}ce"] {
+            assert!(unicode(username).is_err(), "{username:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn folds_case_only_when_asked() {
+        let fold = |username| normalize_username(username, UsernameCharset::Unicode, true);
+        assert_eq!(fold("ÅSA").unwrap(), "åsa");
+        assert_eq!(fold("A\u{030A}SA").unwrap(), "åsa");
+        assert_eq!(fold("Straße").unwrap(), "straße");
+        assert_eq!(unicode("ÅSA").unwrap(), "ÅSA");
+    }
+
+    #[test]
+    fn ascii_rejects_look_alikes() {
+        assert_eq!(ascii("alice.b_c-1").unwrap(), "alice.b_c-1");
+        // Cyrillic а, е and о look like their Latin counterparts
+        for username in ["\u{0430}lice", "b\u{043E}b", "\u{0435}ve"] {
+            let error = ascii(username).unwrap_err();
+            assert!(error.contains("ASCII"), "{error}");
+        }
+        assert!(ascii("José").is_err());
+        assert!(ascii("ali ce").is_err());
+    }
+}